@@ -0,0 +1,201 @@
+//! Derive macros for [`sequelles`](https://docs.rs/sequelles). Use them through the `derive` feature of `sequelles`.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use quote::quote_spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Field;
+use syn::Fields;
+use syn::Index;
+use syn::Member;
+use syn::Type;
+use syn::parse_macro_input;
+use syn::spanned::Spanned as _;
+
+/// Derive `HasRowID` for a struct.
+///
+/// The rowid is taken from:
+/// - The field marked with `#[rowid]`
+/// - Else, the field named `rowid` or `id`
+/// - Else, the only field of a newtype
+///
+/// The field must be an `i64`, or a type implementing `HasRowID`, like another row struct.
+#[proc_macro_derive(HasRowID, attributes(rowid))]
+pub fn derive_has_rowid(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Derive `SetRowID` for a struct. The rowid field is found the same way as [`HasRowID`](macro@HasRowID)
+#[proc_macro_derive(SetRowID, attributes(rowid))]
+pub fn derive_set_rowid(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand_set(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let (member, predicates) = prepare(input, "HasRowID")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::sequelles::has_rowid::HasRowID for #name #ty_generics
+        where
+            #(#predicates,)*
+        {
+            fn rowid(&self) -> i64 {
+                ::sequelles::has_rowid::HasRowID::rowid(&self.#member)
+            }
+        }
+    })
+}
+
+fn expand_set(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let (member, predicates) = prepare(input, "SetRowID")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::sequelles::has_rowid::SetRowID for #name #ty_generics
+        where
+            #(#predicates,)*
+        {
+            fn set_rowid(&mut self, rowid: i64) {
+                ::sequelles::has_rowid::SetRowID::set_rowid(&mut self.#member, rowid)
+            }
+        }
+    })
+}
+
+/// Find the rowid field, and the where clause of the impl, requiring the field to implement `trait_name`
+fn prepare(
+    input: &DeriveInput,
+    trait_name: &str,
+) -> syn::Result<(Member, Vec<syn::WherePredicate>)> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            format!("`{trait_name}` can only be derived on structs"),
+        ));
+    };
+
+    let (member, field) = find_rowid_field(&data.fields, input)?;
+    check_field_type(&field.ty)?;
+
+    let mut predicates = input
+        .generics
+        .where_clause
+        .as_ref()
+        .map(|clause| clause.predicates.iter().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    // Spanned on the field, so a type without rowid is reported there
+    let ty = &field.ty;
+    let trait_ident = syn::Ident::new(trait_name, Span::call_site());
+    predicates.push(syn::parse2(
+        quote_spanned! {ty.span()=> #ty: ::sequelles::has_rowid::#trait_ident},
+    )?);
+
+    Ok((member, predicates))
+}
+
+fn find_rowid_field<'f>(
+    fields: &'f Fields,
+    input: &DeriveInput,
+) -> syn::Result<(Member, &'f Field)> {
+    let members = fields.iter().enumerate().map(|(i, field)| {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        };
+        (member, field)
+    });
+
+    let marked = members
+        .clone()
+        .filter(|(_, field)| field.attrs.iter().any(|attr| attr.path().is_ident("rowid")))
+        .collect::<Vec<_>>();
+
+    for (_, field) in &marked {
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("rowid"))
+        {
+            attr.meta.require_path_only()?;
+        }
+    }
+
+    match marked.as_slice() {
+        [found] => return Ok(found.clone()),
+        [_, second, ..] => {
+            return Err(syn::Error::new(
+                second.1.span(),
+                "only one field can be marked with `#[rowid]`",
+            ));
+        }
+        [] => {}
+    }
+
+    let named = members
+        .clone()
+        .filter(|(_, field)| {
+            field
+                .ident
+                .as_ref()
+                .is_some_and(|ident| ident == "rowid" || ident == "id")
+        })
+        .collect::<Vec<_>>();
+
+    match named.as_slice() {
+        [found] => return Ok(found.clone()),
+        [_, second, ..] => {
+            return Err(syn::Error::new(
+                second.1.span(),
+                "both `id` and `rowid` fields exist, mark the rowid with `#[rowid]`",
+            ));
+        }
+        [] => {}
+    }
+
+    if let Fields::Unnamed(_) = fields {
+        if let [found] = members.collect::<Vec<_>>().as_slice() {
+            return Ok(found.clone());
+        }
+    }
+
+    Err(syn::Error::new(
+        input.ident.span(),
+        "no rowid field found, name it `id` or `rowid`, or mark it with `#[rowid]`",
+    ))
+}
+
+/// Reject the common types that can't be rowids with a clearer message than the missing trait
+fn check_field_type(ty: &Type) -> syn::Result<()> {
+    const NOT_ROWIDS: &[&str] = &[
+        "i8", "i16", "i32", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32",
+        "f64", "bool", "char", "str", "String",
+    ];
+
+    let Type::Path(path) = ty else {
+        return Ok(());
+    };
+
+    match path.path.get_ident() {
+        Some(ident) if NOT_ROWIDS.iter().any(|name| ident == name) => Err(syn::Error::new(
+            ty.span(),
+            format!("a rowid must be an `i64`, not a `{ident}`"),
+        )),
+        _ => Ok(()),
+    }
+}
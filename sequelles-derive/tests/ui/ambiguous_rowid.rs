@@ -0,0 +1,11 @@
+use sequelles_derive::HasRowID;
+
+#[derive(HasRowID)]
+struct Listen {
+    #[rowid]
+    id: i64,
+    #[rowid]
+    recording: i64,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use sequelles_derive::HasRowID;
+
+#[derive(HasRowID)]
+enum Listen {
+    Recording { id: i64 },
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use sequelles_derive::HasRowID;
+
+#[derive(HasRowID)]
+struct Listen {
+    id: i64,
+    rowid: i64,
+}
+
+fn main() {}
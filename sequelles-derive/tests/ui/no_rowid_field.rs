@@ -0,0 +1,9 @@
+use sequelles_derive::HasRowID;
+
+#[derive(HasRowID)]
+struct Listen {
+    timestamp: i64,
+    user: String,
+}
+
+fn main() {}
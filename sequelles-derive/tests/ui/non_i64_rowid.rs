@@ -0,0 +1,8 @@
+use sequelles_derive::HasRowID;
+
+#[derive(HasRowID)]
+struct Listen {
+    id: u32,
+}
+
+fn main() {}
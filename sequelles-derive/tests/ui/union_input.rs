@@ -0,0 +1,8 @@
+use sequelles_derive::HasRowID;
+
+#[derive(HasRowID)]
+union Listen {
+    id: i64,
+}
+
+fn main() {}
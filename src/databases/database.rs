@@ -0,0 +1,88 @@
+use core::error::Error;
+use core::ops::DerefMut;
+
+use snafu::ResultExt as _;
+use snafu::Snafu;
+use sqlx::Executor;
+use sqlx::FromRow;
+use sqlx::IntoArguments;
+use sqlx::query::QueryAs;
+
+use crate::JoinCollection;
+use crate::JoinRelation;
+use crate::Table;
+use crate::has_rowid::HasRowID;
+
+/// A database handler, independent of its backend.
+///
+/// Implemented by [`SqliteDatabase`](crate::databases::sqlite::database::SqliteDatabase),
+/// and [`PostgresDatabase`](crate::databases::postgres::database::PostgresDatabase) with the `postgres` feature
+pub trait Database: Send + Sync {
+    /// The sqlx database backend
+    type Backend: sqlx::Database;
+
+    /// A connection given by the handler. Once dropped, it should return to the pool, if any
+    type Connection: DerefMut<Target = <Self::Backend as sqlx::Database>::Connection> + Send;
+
+    type Error: Error + Send + Sync + 'static;
+
+    /// Get a connection toward the database
+    fn get_conn(&self) -> impl Future<Output = Result<Self::Connection, Self::Error>> + Send;
+
+    /// Make sure all the migrations of the database are applied
+    fn migrate(&self) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Check that the database can be reached, and answers
+    fn health(&self) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Run the query and collect the rows into a [`Table`]
+    fn fetch_table<'q, R, A>(
+        &self,
+        query: QueryAs<'q, Self::Backend, R, A>,
+    ) -> impl Future<Output = Result<Table<R>, FetchError<Self::Error>>> + Send
+    where
+        R: HasRowID + Send + Unpin + for<'r> FromRow<'r, <Self::Backend as sqlx::Database>::Row>,
+        A: 'q + IntoArguments<'q, Self::Backend> + Send,
+        for<'c> &'c mut <Self::Backend as sqlx::Database>::Connection:
+            Executor<'c, Database = Self::Backend>,
+    {
+        async move {
+            let mut conn = self.get_conn().await.context(ConnectionSnafu)?;
+            Table::fetch_all(query, &mut *conn)
+                .await
+                .context(QuerySnafu)
+        }
+    }
+
+    /// Run the query and collect the rows into a [`JoinCollection`]
+    fn fetch_join<'q, R, A>(
+        &self,
+        query: QueryAs<'q, Self::Backend, JoinRelation<R>, A>,
+    ) -> impl Future<Output = Result<JoinCollection<R>, FetchError<Self::Error>>> + Send
+    where
+        JoinRelation<R>: Send + Unpin + for<'r> FromRow<'r, <Self::Backend as sqlx::Database>::Row>,
+        A: 'q + IntoArguments<'q, Self::Backend> + Send,
+        for<'c> &'c mut <Self::Backend as sqlx::Database>::Connection:
+            Executor<'c, Database = Self::Backend>,
+    {
+        async move {
+            let mut conn = self.get_conn().await.context(ConnectionSnafu)?;
+            JoinCollection::fetch(query, &mut *conn)
+                .await
+                .context(QuerySnafu)
+        }
+    }
+}
+
+/// An error while fetching data from a [`Database`]
+#[derive(Debug, Snafu)]
+pub enum FetchError<E>
+where
+    E: Error + 'static,
+{
+    #[snafu(display("Could not get a connection from the database"))]
+    ConnectionError { source: E },
+
+    #[snafu(display("Could not fetch the rows"))]
+    QueryError { source: sqlx::Error },
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use async_once_cell::OnceCell;
+use bon::Builder;
+use deadpool::managed::Object;
+use deadpool::managed::PoolConfig;
+use snafu::Backtrace;
+use snafu::ResultExt;
+use snafu::Snafu;
+use sqlx::Connection as _;
+use sqlx::PgConnection;
+use sqlx::Postgres;
+use sqlx::migrate::Migrator;
+use sqlx::postgres::PgConnectOptions;
+
+use crate::databases::database::Database;
+use crate::databases::postgres::database::pool::PoolInitError;
+use crate::databases::postgres::pool::PgPool;
+use crate::databases::postgres::pool::PgPoolConnection;
+use crate::databases::postgres::pool::PgPoolError;
+
+pub mod pool;
+
+pub type ArcPostgresDatabase = Arc<PostgresDatabase>;
+
+/// All in one Postgres database handler. Support connection pooling and migrations.
+#[derive(Debug, Builder)]
+pub struct PostgresDatabase {
+    /// The configuration of the connection
+    pub connection_config: PgConnectOptions,
+
+    /// The configuration of the pool
+    pub pool_config: Option<PoolConfig>,
+
+    /// The migrations of the database. If provided, they will be automatically be done on pool creation
+    migrations: Option<Migrator>,
+
+    #[builder(skip)]
+    pool: OnceCell<PgPool>,
+}
+
+impl PostgresDatabase {
+    /// Get a connection toward the database. Once dropped, it will return to the pool.
+    ///
+    /// This initialize the pool if it isn't ready yet.
+    pub async fn get_conn(&self) -> Result<PgPoolConnection, GetConnectionError> {
+        self.get_pool_or_init()
+            .await
+            .context(PoolInitSnafu)?
+            .get()
+            .await
+            .context(ConnectionSnafu)
+    }
+
+    /// Get a connection toward the database. Once dropped, it will **not** return to the pool. You can use this to prevent RAII guard lifetime problems.
+    ///
+    /// This initialize the pool if it isn't ready yet.
+    pub async fn get_conn_owned(&self) -> Result<PgConnection, GetConnectionError> {
+        self.get_conn().await.map(Object::take)
+    }
+}
+
+impl Database for PostgresDatabase {
+    type Backend = Postgres;
+    type Connection = PgPoolConnection;
+    type Error = GetConnectionError;
+
+    async fn get_conn(&self) -> Result<Self::Connection, Self::Error> {
+        PostgresDatabase::get_conn(self).await
+    }
+
+    /// The migrations are applied on pool creation, so this only initialize the pool
+    async fn migrate(&self) -> Result<(), Self::Error> {
+        self.get_pool_or_init().await.context(PoolInitSnafu)?;
+        Ok(())
+    }
+
+    async fn health(&self) -> Result<(), Self::Error> {
+        PostgresDatabase::get_conn(self)
+            .await?
+            .ping()
+            .await
+            .context(UnresponsiveSnafu)
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum GetConnectionError {
+    #[snafu(display("Could not create the pool for the database"))]
+    PoolInitError {
+        #[snafu(backtrace)]
+        source: PoolInitError,
+    },
+
+    #[snafu(display("Could not get a connection from the database"))]
+    ConnectionError {
+        backtrace: Backtrace,
+        source: PgPoolError,
+    },
+
+    #[snafu(display("The database connection didn't answer"))]
+    UnresponsiveError {
+        backtrace: Backtrace,
+        source: sqlx::Error,
+    },
+}
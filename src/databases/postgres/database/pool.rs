@@ -0,0 +1,117 @@
+use async_once_cell::OnceCell;
+use snafu::Backtrace;
+use snafu::ResultExt as _;
+use snafu::Snafu;
+use sqlx::migrate::MigrateError;
+
+use crate::databases::postgres::database::PostgresDatabase;
+use crate::databases::postgres::pool::PgPool;
+use crate::databases::postgres::pool::PgPoolError;
+use crate::databases::postgres::pool::PgPoolManager;
+
+impl PostgresDatabase {
+    pub async fn init_pool<F>(&self, pool: F) -> Result<PgPool, PoolInitError>
+    where
+        F: FnOnce() -> PgPool,
+    {
+        let pool = pool();
+
+        if let Some(migrator) = self.migrations.as_ref() {
+            let mut conn = pool.get().await.context(ConnectionSnafu)?;
+
+            // Unlike sqlite, postgres connections do their I/O on the sockets of the calling runtime, so the migrations must be awaited.
+            // `run_direct` skips the `Acquire` bound that makes `Migrator::run` not `Send`. It is public since sqlx 0.8.0, but hidden from the docs.
+            // See: https://github.com/launchbadge/sqlx/issues/954
+            migrator
+                .run_direct(&mut *conn)
+                .await
+                .context(MigrationSnafu)?
+        }
+
+        Ok(pool)
+    }
+
+    /// Initialize the internal pool. Useful to pass in a custom pool
+    ///
+    /// Does nothing if the pool is already initialized
+    pub async fn get_pool_or_init_with<F>(&self, pool: F) -> Result<&PgPool, PoolInitError>
+    where
+        F: FnOnce() -> PgPool,
+    {
+        self.pool.get_or_try_init(self.init_pool(pool)).await
+    }
+
+    /// Get the inner pool or initialize it and return it
+    pub async fn get_pool_or_init(&self) -> Result<&PgPool, PoolInitError> {
+        self.get_pool_or_init_with(move || {
+            PgPool::builder(PgPoolManager::new(self.connection_config.to_owned()))
+                .config(self.pool_config.to_owned().unwrap_or_default())
+                .build()
+                .expect("Couldn't build the postgres pool")
+        })
+        .await
+    }
+
+    /// Close the connection pool by dropping it.
+    ///
+    /// It isn't closed forever, as it may be reopened at anytime be calling [PostgresDatabase::get_pool_or_init] or [PostgresDatabase::get_conn]
+    pub fn close_pool(&mut self) {
+        self.pool = OnceCell::new()
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum PoolInitError {
+    #[snafu(display("Could not get a connection from the database"))]
+    ConnectionError {
+        backtrace: Backtrace,
+        source: PgPoolError,
+    },
+
+    #[snafu(display("Could not apply the migrations"))]
+    MigrationError {
+        backtrace: Backtrace,
+        source: MigrateError,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use sqlx::migrate::Migration;
+    use sqlx::migrate::MigrationType;
+    use sqlx::migrate::Migrator;
+    use sqlx::postgres::PgConnectOptions;
+
+    use crate::databases::postgres::database::PostgresDatabase;
+
+    #[tokio::test]
+    #[ignore = "needs a postgres server at `DATABASE_URL`"]
+    async fn init_pool_migrations_test() {
+        let url = std::env::var("DATABASE_URL").expect("`DATABASE_URL` should be set");
+        let migrator = Migrator {
+            migrations: Cow::Owned(vec![Migration::new(
+                1,
+                "pool test".into(),
+                MigrationType::Simple,
+                "CREATE TABLE sequelles_pool_test (id BIGINT PRIMARY KEY);".into(),
+                false,
+            )]),
+            ..Migrator::DEFAULT
+        };
+
+        let db = PostgresDatabase::builder()
+            .connection_config(url.parse::<PgConnectOptions>().unwrap())
+            .migrations(migrator)
+            .build();
+
+        // The default test runtime is single threaded, where blocking on the migrations would deadlock
+        let mut conn = db.get_conn().await.unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT count(*) FROM sequelles_pool_test")
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
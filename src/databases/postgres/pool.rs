@@ -0,0 +1,48 @@
+use deadpool::managed;
+use deadpool::managed::Object;
+use deadpool::managed::PoolError;
+use sqlx::Connection as _;
+use sqlx::PgConnection;
+use sqlx::postgres::PgConnectOptions;
+
+/// A [deadpool] manager for a postgres database
+#[derive(Debug)]
+pub struct PgPoolManager {
+    config: PgConnectOptions,
+}
+
+impl PgPoolManager {
+    pub fn new(config: PgConnectOptions) -> Self {
+        Self { config }
+    }
+
+    pub fn create_pool(config: PgConnectOptions) -> PgPool {
+        PgPool::builder(PgPoolManager::new(config)).build().unwrap()
+    }
+}
+
+impl managed::Manager for PgPoolManager {
+    type Type = sqlx::PgConnection;
+    type Error = sqlx::Error;
+
+    async fn create(&self) -> Result<Self::Type, Self::Error> {
+        PgConnection::connect_with(&self.config).await
+    }
+
+    async fn recycle(
+        &self,
+        conn: &mut Self::Type,
+        _: &managed::Metrics,
+    ) -> managed::RecycleResult<Self::Error> {
+        Ok(conn.ping().await?)
+    }
+}
+
+/// A [deadpool] of postgres connections
+pub type PgPool = managed::Pool<PgPoolManager>;
+
+pub type PgPoolError = PoolError<sqlx::Error>;
+
+pub type PgPoolConnection = Object<PgPoolManager>;
+
+pub type PgPoolResult = Result<PgPoolConnection, PgPoolError>;
@@ -1 +1,278 @@
+use core::ops::Deref;
+use core::ops::DerefMut;
+use std::time::Instant;
 
+use deadpool::managed::Object;
+use deadpool::managed::ObjectId;
+use sqlx::Connection as _;
+use sqlx::FromRow;
+use sqlx::Sqlite;
+use sqlx::SqliteConnection;
+use sqlx::Transaction;
+use sqlx::query::QueryAs;
+use sqlx::sqlite::SqliteArguments;
+use sqlx::sqlite::SqliteRow;
+
+use crate::JoinCollection;
+use crate::JoinRelation;
+use crate::Table;
+use crate::databases::sqlite::database::GetConnectionError;
+use crate::databases::sqlite::database::SqliteDatabase;
+use crate::databases::sqlite::pool::SqlitePoolConnection;
+use crate::has_rowid::HasRowID;
+
+/// A pooled connection with some bookkeeping, and shortcuts to fetch data into this crate's structures.
+///
+/// It derefs to the inner [`SqliteConnection`], so `&mut *conn` can be used as an [`sqlx::Executor`]
+#[derive(Debug)]
+pub struct SequellesConnection {
+    conn: SqlitePoolConnection,
+    open_transactions: usize,
+}
+
+impl SequellesConnection {
+    pub fn new(conn: SqlitePoolConnection) -> Self {
+        Self {
+            conn,
+            open_transactions: 0,
+        }
+    }
+
+    /// The id of the connection in its pool
+    pub fn id(&self) -> ObjectId {
+        Object::id(&self.conn)
+    }
+
+    /// When the underlying connection was opened
+    pub fn created_at(&self) -> Instant {
+        Object::metrics(&self.conn).created
+    }
+
+    /// How many times the underlying connection has been taken out of the pool, this time included
+    pub fn use_count(&self) -> usize {
+        Object::metrics(&self.conn).recycle_count + 1
+    }
+
+    /// The number of transactions started with [`SequellesConnection::begin`] that are still running, nested ones included.
+    ///
+    /// The connection is borrowed by its transaction, so use [`SequellesTransaction::open_transactions`] while it runs
+    pub fn open_transactions(&self) -> usize {
+        self.open_transactions
+    }
+
+    /// Return the connection as an executor
+    pub fn executor(&mut self) -> &mut SqliteConnection {
+        &mut self.conn
+    }
+
+    /// Start a new tracked transaction
+    pub async fn begin(&mut self) -> Result<SequellesTransaction<'_>, sqlx::Error> {
+        let Self {
+            conn,
+            open_transactions,
+        } = self;
+
+        let transaction = conn.begin().await?;
+        *open_transactions += 1;
+
+        Ok(SequellesTransaction {
+            transaction: Some(transaction),
+            open_transactions,
+        })
+    }
+
+    /// Run the query and collect the rows into a [`Table`]
+    pub async fn fetch_table<'q, R>(
+        &mut self,
+        query: QueryAs<'q, Sqlite, R, SqliteArguments<'q>>,
+    ) -> Result<Table<R>, sqlx::Error>
+    where
+        R: HasRowID + Send + Unpin + for<'r> FromRow<'r, SqliteRow>,
+    {
+        Table::fetch_all(query, self.executor()).await
+    }
+
+    /// Run the query and collect the rows into a [`JoinCollection`]
+    pub async fn fetch_join<'q, R>(
+        &mut self,
+        query: QueryAs<'q, Sqlite, JoinRelation<R>, SqliteArguments<'q>>,
+    ) -> Result<JoinCollection<R>, sqlx::Error>
+    where
+        JoinRelation<R>: Send + Unpin + for<'r> FromRow<'r, SqliteRow>,
+    {
+        JoinCollection::fetch(query, self.executor()).await
+    }
+
+    /// Return the inner pooled connection
+    pub fn into_inner(self) -> SqlitePoolConnection {
+        self.conn
+    }
+}
+
+impl From<SqlitePoolConnection> for SequellesConnection {
+    fn from(value: SqlitePoolConnection) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Deref for SequellesConnection {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl DerefMut for SequellesConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+/// A transaction tracked by its [`SequellesConnection`]. Like [`Transaction`], it is rolled back if dropped without being commited.
+#[derive(Debug)]
+pub struct SequellesTransaction<'c> {
+    // Only `None` once commited or rolled back
+    transaction: Option<Transaction<'c, Sqlite>>,
+    open_transactions: &'c mut usize,
+}
+
+impl SequellesTransaction<'_> {
+    /// The number of transactions of the connection that are still running, this one and the nested ones included
+    pub fn open_transactions(&self) -> usize {
+        *self.open_transactions
+    }
+
+    /// Start a nested transaction, using a savepoint. It is tracked by the connection too
+    pub async fn begin(&mut self) -> Result<SequellesTransaction<'_>, sqlx::Error> {
+        let Self {
+            transaction,
+            open_transactions,
+        } = self;
+
+        let nested = transaction
+            .as_mut()
+            .expect("The transaction should be running")
+            .begin()
+            .await?;
+        **open_transactions += 1;
+
+        Ok(SequellesTransaction {
+            transaction: Some(nested),
+            open_transactions,
+        })
+    }
+
+    /// See [`Transaction::commit`]
+    pub async fn commit(mut self) -> Result<(), sqlx::Error> {
+        self.transaction
+            .take()
+            .expect("The transaction should be running")
+            .commit()
+            .await
+    }
+
+    /// See [`Transaction::rollback`]
+    pub async fn rollback(mut self) -> Result<(), sqlx::Error> {
+        self.transaction
+            .take()
+            .expect("The transaction should be running")
+            .rollback()
+            .await
+    }
+}
+
+impl Deref for SequellesTransaction<'_> {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &Self::Target {
+        self.transaction
+            .as_ref()
+            .expect("The transaction should be running")
+    }
+}
+
+impl DerefMut for SequellesTransaction<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.transaction
+            .as_mut()
+            .expect("The transaction should be running")
+    }
+}
+
+impl Drop for SequellesTransaction<'_> {
+    fn drop(&mut self) {
+        *self.open_transactions -= 1;
+    }
+}
+
+impl SqliteDatabase {
+    /// Get a [`SequellesConnection`] toward the database. Once dropped, it will return to the pool.
+    ///
+    /// This initialize the pool if it isn't ready yet.
+    pub async fn get_sequelles_conn(&self) -> Result<SequellesConnection, GetConnectionError> {
+        self.get_conn().await.map(SequellesConnection::new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use deadpool::managed::PoolConfig;
+    use sqlx::Executor as _;
+    use sqlx::FromRow;
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    use crate::databases::sqlite::database::SqliteDatabase;
+    use crate::has_rowid::HasRowID;
+
+    #[derive(Debug, FromRow)]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    impl HasRowID for User {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_test() {
+        let db = SqliteDatabase::builder()
+            .connection_config(SqliteConnectOptions::new().in_memory(true))
+            .pool_config(PoolConfig::new(1))
+            .build();
+
+        let mut conn = db.get_sequelles_conn().await.unwrap();
+        assert_eq!(conn.use_count(), 1);
+
+        let mut tx = conn.begin().await.unwrap();
+        tx.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL); INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob');")
+            .await
+            .unwrap();
+        assert_eq!(tx.open_transactions(), 1);
+
+        let mut nested = tx.begin().await.unwrap();
+        assert_eq!(nested.open_transactions(), 2);
+        nested
+            .execute("INSERT INTO users VALUES (3, 'Carol');")
+            .await
+            .unwrap();
+        nested.rollback().await.unwrap();
+        assert_eq!(tx.open_transactions(), 1);
+
+        tx.commit().await.unwrap();
+        assert_eq!(conn.open_transactions(), 0);
+
+        let users = conn
+            .fetch_table(sqlx::query_as::<_, User>("SELECT * FROM users"))
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users.get(2).unwrap().name, "Bob");
+
+        drop(conn);
+        assert_eq!(db.get_sequelles_conn().await.unwrap().use_count(), 2);
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read as _;
+use std::io::Seek as _;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+use fs4::FileExt;
+use fs4::TryLockError;
+use snafu::Backtrace;
+use snafu::OptionExt as _;
+use snafu::ResultExt as _;
+use snafu::Snafu;
+
+use crate::databases::sqlite::database::SqliteDatabase;
+
+/// An advisory lock on a database file, held by this process for as long as the guard is alive.
+///
+/// The lock is a `.lock` file next to the database that contains the PID of the owning process.
+/// It is only advisory: processes that don't try to take the lock won't be stopped.
+#[derive(Debug)]
+pub struct DatabaseLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl DatabaseLock {
+    /// Try to acquire the lock file at `path`, without waiting for the other process to release it.
+    pub fn try_acquire(path: PathBuf) -> Result<Self, DatabaseLockError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .context(LockFileSnafu { path: &path })?;
+
+        // Explicitly call the fs4 method, as `File::try_lock` shadows it on newer toolchains
+        match FileExt::try_lock(&file) {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return AlreadyLockedSnafu {
+                    pid: read_pid(&mut file),
+                }
+                .fail();
+            }
+            Err(TryLockError::Error(err)) => return Err(err).context(LockFileSnafu { path }),
+        }
+
+        write_pid(&mut file).context(LockFileSnafu { path: &path })?;
+
+        Ok(Self { file, path })
+    }
+
+    /// The path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DatabaseLock {
+    fn drop(&mut self) {
+        // The lock itself is released when the file is closed. We only clear the PID so it doesn't look stale.
+        let _ = self.file.set_len(0);
+    }
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+fn write_pid(file: &mut File) -> io::Result<()> {
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    file.sync_all()
+}
+
+impl SqliteDatabase {
+    /// The path of the lock file of the database. This is the database path with an added `.lock` extension.
+    pub fn lock_path(&self) -> Option<PathBuf> {
+        let mut path = self.path.as_ref()?.as_os_str().to_owned();
+        path.push(".lock");
+        Some(path.into())
+    }
+
+    /// Try to acquire the cross-process lock of the database file.
+    ///
+    /// This is done automatically on pool creation if `single_writer_lock` is set,
+    /// but can be used manually to guard other operations on the file.
+    pub fn try_lock(&self) -> Result<DatabaseLock, DatabaseLockError> {
+        DatabaseLock::try_acquire(self.lock_path().context(MissingPathSnafu)?)
+    }
+
+    /// Acquire the lock of the database and keep it until the database is dropped.
+    ///
+    /// Does nothing if the lock is already held by this database.
+    pub fn acquire_lock(&self) -> Result<(), DatabaseLockError> {
+        self.acquire_new_lock().map(|_| ())
+    }
+
+    /// Acquire the lock of the database, and return whether it wasn't already held
+    pub(crate) fn acquire_new_lock(&self) -> Result<bool, DatabaseLockError> {
+        let mut lock = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+
+        if lock.is_some() {
+            return Ok(false);
+        }
+
+        *lock = Some(self.try_lock()?);
+        Ok(true)
+    }
+
+    /// Release the lock held by the database, if any.
+    pub fn release_lock(&self) {
+        *self.lock.lock().unwrap_or_else(|err| err.into_inner()) = None;
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum DatabaseLockError {
+    #[snafu(display("The database has no path to create a lock file next to"))]
+    MissingPathError { backtrace: Backtrace },
+
+    #[snafu(display("Could not lock the lock file `{}`", path.display()))]
+    LockFileError {
+        path: PathBuf,
+        backtrace: Backtrace,
+        source: io::Error,
+    },
+
+    #[snafu(display(
+        "The database is already in use by {}",
+        pid.map(|pid| format!("process {pid}")).unwrap_or_else(|| "another process".to_string())
+    ))]
+    AlreadyLockedError {
+        /// The PID of the process holding the lock, if it could be read
+        pid: Option<u32>,
+        backtrace: Backtrace,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use crate::databases::sqlite::database::lock::DatabaseLock;
+    use crate::databases::sqlite::database::lock::DatabaseLockError;
+
+    #[test]
+    fn lock_test() {
+        let path =
+            std::env::temp_dir().join(format!("sequelles_lock_test_{}.lock", std::process::id()));
+
+        let lock = DatabaseLock::try_acquire(path.clone()).unwrap();
+
+        match DatabaseLock::try_acquire(path.clone()) {
+            Err(DatabaseLockError::AlreadyLockedError { pid, .. }) => {
+                assert_eq!(pid, Some(std::process::id()))
+            }
+            other => panic!("Expected the lock to be held, got {other:?}"),
+        }
+
+        drop(lock);
+        assert!(DatabaseLock::try_acquire(path.clone()).is_ok());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use async_once_cell::OnceCell;
 use bon::Builder;
@@ -8,15 +9,21 @@ use deadpool::managed::PoolConfig;
 use snafu::Backtrace;
 use snafu::ResultExt;
 use snafu::Snafu;
+use sqlx::Connection as _;
+use sqlx::Sqlite;
 use sqlx::SqliteConnection;
 use sqlx::migrate::Migrator;
 use sqlx::sqlite::SqliteConnectOptions;
 
+use crate::databases::database::Database;
+use crate::databases::sqlite::database::lock::DatabaseLock;
 use crate::databases::sqlite::database::pool::PoolInitError;
+use crate::databases::sqlite::functions::SqliteFunctions;
 use crate::databases::sqlite::pool::SqlitePool;
 use crate::databases::sqlite::pool::SqlitePoolConnection;
 use crate::databases::sqlite::pool::SqlitePoolError;
 
+pub mod lock;
 pub mod pool;
 
 pub type ArcSqliteDatabase = Arc<SqliteDatabase>;
@@ -35,8 +42,20 @@ pub struct SqliteDatabase {
     /// The migrations of the database. If provided, they will be automatically be done on pool creation
     migrations: Option<Migrator>,
 
+    /// Custom functions and collations to install on every connection of the pool
+    #[builder(default)]
+    pub functions: SqliteFunctions,
+
+    /// If set, take a lock file next to [`SqliteDatabase::path`] on pool creation,
+    /// preventing other processes from migrating or writing the database at the same time.
+    #[builder(default)]
+    pub single_writer_lock: bool,
+
     #[builder(skip)]
     pool: OnceCell<SqlitePool>,
+
+    #[builder(skip)]
+    lock: Mutex<Option<DatabaseLock>>,
 }
 
 impl SqliteDatabase {
@@ -60,6 +79,30 @@ impl SqliteDatabase {
     }
 }
 
+impl Database for SqliteDatabase {
+    type Backend = Sqlite;
+    type Connection = SqlitePoolConnection;
+    type Error = GetConnectionError;
+
+    async fn get_conn(&self) -> Result<Self::Connection, Self::Error> {
+        SqliteDatabase::get_conn(self).await
+    }
+
+    /// The migrations are applied on pool creation, so this only initialize the pool
+    async fn migrate(&self) -> Result<(), Self::Error> {
+        self.get_pool_or_init().await.context(PoolInitSnafu)?;
+        Ok(())
+    }
+
+    async fn health(&self) -> Result<(), Self::Error> {
+        SqliteDatabase::get_conn(self)
+            .await?
+            .ping()
+            .await
+            .context(UnresponsiveSnafu)
+    }
+}
+
 #[derive(Debug, Snafu)]
 pub enum GetConnectionError {
     #[snafu(display("Could not create the pool for the database"))]
@@ -73,4 +116,10 @@ pub enum GetConnectionError {
         backtrace: Backtrace,
         source: SqlitePoolError,
     },
+
+    #[snafu(display("The database connection didn't answer"))]
+    UnresponsiveError {
+        backtrace: Backtrace,
+        source: sqlx::Error,
+    },
 }
@@ -6,6 +6,7 @@ use sqlx::SqliteConnection;
 use sqlx::migrate::MigrateError;
 
 use crate::databases::sqlite::database::SqliteDatabase;
+use crate::databases::sqlite::database::lock::DatabaseLockError;
 use crate::databases::sqlite::pool::SqlitePool;
 use crate::databases::sqlite::pool::SqlitePoolError;
 use crate::databases::sqlite::pool::SqlitePoolManager;
@@ -16,8 +17,19 @@ impl SqliteDatabase {
         F: FnOnce() -> SqlitePool,
         SqliteConnection:,
     {
-        let pool = pool();
+        let locked = self.single_writer_lock && self.acquire_new_lock().context(LockSnafu)?;
 
+        let result = self.migrate_pool(pool()).await;
+
+        // Don't keep the database locked without a pool, or the next initialization couldn't take the lock
+        if result.is_err() && locked {
+            self.release_lock();
+        }
+
+        result
+    }
+
+    async fn migrate_pool(&self, pool: SqlitePool) -> Result<SqlitePool, PoolInitError> {
         if let Some(migrator) = self.migrations.as_ref() {
             let conn = &mut *pool.get().await.context(ConnectionSnafu)?;
 
@@ -48,10 +60,13 @@ impl SqliteDatabase {
     /// Get the inner pool or initialize it and return it
     pub async fn get_pool_or_init(&self) -> Result<&SqlitePool, PoolInitError> {
         self.get_pool_or_init_with(move || {
-            SqlitePool::builder(SqlitePoolManager::new(self.connection_config.to_owned()))
-                .config(self.pool_config.to_owned().unwrap_or_default())
-                .build()
-                .expect("Couldn't build the sqlite pool")
+            SqlitePool::builder(
+                SqlitePoolManager::new(self.connection_config.to_owned())
+                    .with_functions(self.functions.clone()),
+            )
+            .config(self.pool_config.to_owned().unwrap_or_default())
+            .build()
+            .expect("Couldn't build the sqlite pool")
         })
         .await
     }
@@ -59,13 +74,25 @@ impl SqliteDatabase {
     /// Close the connection pool by dropping it.
     ///
     /// It isn't closed forever, as it may be reopened at anytime be calling [SqliteDatabase::get_pool_or_init] or [SqliteDatabase::get_conn]
+    ///
+    /// If `single_writer_lock` is set, the lock of the database is released too.
     pub fn close_pool(&mut self) {
-        self.pool = OnceCell::new()
+        self.pool = OnceCell::new();
+
+        if self.single_writer_lock {
+            self.release_lock();
+        }
     }
 }
 
 #[derive(Debug, Snafu)]
 pub enum PoolInitError {
+    #[snafu(display("Could not lock the database"))]
+    LockError {
+        #[snafu(backtrace)]
+        source: DatabaseLockError,
+    },
+
     #[snafu(display("Could not get a connection from the database"))]
     ConnectionError {
         backtrace: Backtrace,
@@ -78,3 +105,53 @@ pub enum PoolInitError {
         source: MigrateError,
     },
 }
+
+#[cfg(test)]
+mod test {
+    use sqlx::migrate::Migrator;
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    use crate::databases::sqlite::database::SqliteDatabase;
+    use crate::databases::sqlite::database::pool::PoolInitError;
+
+    async fn database(dir: &std::path::Path, migration: &str) -> SqliteDatabase {
+        let migrations = dir.join("migrations");
+        std::fs::create_dir_all(&migrations).unwrap();
+        std::fs::write(migrations.join("1_init.sql"), migration).unwrap();
+
+        let path = dir.join("db.sqlite");
+        SqliteDatabase::builder()
+            .connection_config(
+                SqliteConnectOptions::new()
+                    .filename(&path)
+                    .create_if_missing(true),
+            )
+            .path(path)
+            .migrations(Migrator::new(migrations).await.unwrap())
+            .single_writer_lock(true)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn lock_release_test() {
+        let dir = std::env::temp_dir().join(format!("sequelles_pool_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let failing = database(&dir, "NOT SQL;").await;
+        assert!(matches!(
+            failing.get_pool_or_init().await,
+            Err(PoolInitError::MigrationError { .. })
+        ));
+
+        // Another database can be initialized, as the lock was released on the failed initialization
+        let mut db = database(&dir, "CREATE TABLE users (id INTEGER PRIMARY KEY);").await;
+        db.get_pool_or_init().await.unwrap();
+        drop(failing);
+        assert!(db.try_lock().is_err());
+
+        db.close_pool();
+        assert!(db.try_lock().is_ok());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
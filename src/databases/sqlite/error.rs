@@ -0,0 +1,233 @@
+use deadpool::managed::PoolError;
+use sqlx::migrate::MigrateError;
+
+use crate::databases::sqlite::database::GetConnectionError;
+use crate::databases::sqlite::database::pool::PoolInitError;
+
+// Sqlite result codes. See: https://www.sqlite.org/rescode.html
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+const SQLITE_READONLY: i32 = 8;
+const SQLITE_CORRUPT: i32 = 11;
+const SQLITE_FULL: i32 = 13;
+const SQLITE_SCHEMA: i32 = 17;
+const SQLITE_CONSTRAINT: i32 = 19;
+const SQLITE_NOTADB: i32 = 26;
+
+const SQLITE_CONSTRAINT_CHECK: i32 = 275;
+const SQLITE_CONSTRAINT_FOREIGNKEY: i32 = 787;
+const SQLITE_CONSTRAINT_NOTNULL: i32 = 1299;
+const SQLITE_CONSTRAINT_PRIMARYKEY: i32 = 1555;
+const SQLITE_CONSTRAINT_UNIQUE: i32 = 2067;
+
+/// What went wrong with an sqlite operation, sorted by what can be done about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqliteErrorKind {
+    /// The database or one of its tables is locked by another connection (`SQLITE_BUSY` / `SQLITE_LOCKED`).
+    Busy,
+
+    /// A constraint of the schema has been violated
+    Constraint {
+        kind: ConstraintKind,
+
+        /// The name of the constraint, or the columns it applies to, as reported by sqlite
+        name: Option<String>,
+    },
+
+    /// The database file is malformed, or isn't a database (`SQLITE_CORRUPT` / `SQLITE_NOTADB`)
+    Corrupt,
+
+    /// The disk is full (`SQLITE_FULL`)
+    DiskFull,
+
+    /// The database is opened as read only, or the file cannot be written to (`SQLITE_READONLY`)
+    ReadOnly,
+
+    /// The schema changed while a statement was prepared (`SQLITE_SCHEMA`)
+    SchemaChanged,
+
+    /// No connection of the pool was available in time
+    PoolTimeout,
+
+    /// Any other error
+    Other,
+}
+
+impl SqliteErrorKind {
+    /// Classify a sqlite error from its extended result code and message
+    pub fn from_code(extended_code: i32, message: &str) -> Self {
+        match extended_code & 0xff {
+            SQLITE_BUSY | SQLITE_LOCKED => Self::Busy,
+            SQLITE_READONLY => Self::ReadOnly,
+            SQLITE_CORRUPT | SQLITE_NOTADB => Self::Corrupt,
+            SQLITE_FULL => Self::DiskFull,
+            SQLITE_SCHEMA => Self::SchemaChanged,
+            SQLITE_CONSTRAINT => Self::Constraint {
+                kind: ConstraintKind::from_code(extended_code),
+                name: constraint_name(message),
+            },
+            _ => Self::Other,
+        }
+    }
+
+    /// Return true if the operation may succeed if tried again later
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Busy | Self::SchemaChanged | Self::PoolTimeout)
+    }
+}
+
+/// The type of a violated constraint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintKind {
+    Unique,
+    PrimaryKey,
+    ForeignKey,
+    NotNull,
+    Check,
+    Other,
+}
+
+impl ConstraintKind {
+    fn from_code(extended_code: i32) -> Self {
+        match extended_code {
+            SQLITE_CONSTRAINT_UNIQUE => Self::Unique,
+            SQLITE_CONSTRAINT_PRIMARYKEY => Self::PrimaryKey,
+            SQLITE_CONSTRAINT_FOREIGNKEY => Self::ForeignKey,
+            SQLITE_CONSTRAINT_NOTNULL => Self::NotNull,
+            SQLITE_CONSTRAINT_CHECK => Self::Check,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Sqlite doesn't expose the constraint name, so we fetch it from messages like `UNIQUE constraint failed: users.email`
+fn constraint_name(message: &str) -> Option<String> {
+    message
+        .split_once("constraint failed: ")
+        .map(|(_, name)| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Sort errors into a [`SqliteErrorKind`]
+pub trait ClassifySqliteError {
+    /// Return the kind of the error
+    fn sqlite_kind(&self) -> SqliteErrorKind;
+
+    /// Return true if the operation may succeed if tried again later
+    fn is_retryable(&self) -> bool {
+        self.sqlite_kind().is_retryable()
+    }
+}
+
+impl ClassifySqliteError for sqlx::Error {
+    fn sqlite_kind(&self) -> SqliteErrorKind {
+        match self {
+            sqlx::Error::Database(err) => err
+                .code()
+                .and_then(|code| code.parse().ok())
+                .map(|code| SqliteErrorKind::from_code(code, err.message()))
+                .unwrap_or(SqliteErrorKind::Other),
+            sqlx::Error::PoolTimedOut => SqliteErrorKind::PoolTimeout,
+            _ => SqliteErrorKind::Other,
+        }
+    }
+}
+
+impl ClassifySqliteError for PoolError<sqlx::Error> {
+    fn sqlite_kind(&self) -> SqliteErrorKind {
+        match self {
+            PoolError::Backend(err) => err.sqlite_kind(),
+            PoolError::Timeout(_) => SqliteErrorKind::PoolTimeout,
+            _ => SqliteErrorKind::Other,
+        }
+    }
+}
+
+impl ClassifySqliteError for MigrateError {
+    fn sqlite_kind(&self) -> SqliteErrorKind {
+        match self {
+            MigrateError::Execute(err) => err.sqlite_kind(),
+            MigrateError::ExecuteMigration(err, _) => err.sqlite_kind(),
+            _ => SqliteErrorKind::Other,
+        }
+    }
+}
+
+impl ClassifySqliteError for PoolInitError {
+    fn sqlite_kind(&self) -> SqliteErrorKind {
+        match self {
+            PoolInitError::LockError { .. } => SqliteErrorKind::Other,
+            PoolInitError::ConnectionError { source, .. } => source.sqlite_kind(),
+            PoolInitError::MigrationError { source, .. } => source.sqlite_kind(),
+        }
+    }
+}
+
+impl ClassifySqliteError for GetConnectionError {
+    fn sqlite_kind(&self) -> SqliteErrorKind {
+        match self {
+            GetConnectionError::PoolInitError { source } => source.sqlite_kind(),
+            GetConnectionError::ConnectionError { source, .. } => source.sqlite_kind(),
+            GetConnectionError::UnresponsiveError { source, .. } => source.sqlite_kind(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::Connection as _;
+    use sqlx::Executor as _;
+    use sqlx::SqliteConnection;
+
+    use crate::databases::sqlite::error::ClassifySqliteError as _;
+    use crate::databases::sqlite::error::ConstraintKind;
+    use crate::databases::sqlite::error::SqliteErrorKind;
+
+    #[tokio::test]
+    async fn classify_constraint_test() {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+        conn.execute(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE NOT NULL, age INTEGER CONSTRAINT positive_age CHECK (age > 0))",
+        )
+        .await
+        .unwrap();
+        conn.execute("INSERT INTO users VALUES (1, 'a@example.com', 1)")
+            .await
+            .unwrap();
+
+        let cases = [
+            (
+                "INSERT INTO users VALUES (2, 'a@example.com', 1)",
+                ConstraintKind::Unique,
+                Some("users.email"),
+            ),
+            (
+                "INSERT INTO users VALUES (1, 'b@example.com', 1)",
+                ConstraintKind::PrimaryKey,
+                Some("users.id"),
+            ),
+            (
+                "INSERT INTO users VALUES (2, NULL, 1)",
+                ConstraintKind::NotNull,
+                Some("users.email"),
+            ),
+            (
+                "INSERT INTO users VALUES (2, 'b@example.com', 0)",
+                ConstraintKind::Check,
+                Some("positive_age"),
+            ),
+        ];
+
+        for (query, kind, name) in cases {
+            let err = conn.execute(query).await.unwrap_err();
+            assert_eq!(
+                err.sqlite_kind(),
+                SqliteErrorKind::Constraint {
+                    kind,
+                    name: name.map(ToString::to_string)
+                }
+            );
+            assert!(!err.is_retryable());
+        }
+    }
+}
@@ -0,0 +1,486 @@
+//! Custom SQL functions and collations, installed on every connection of a pool.
+//!
+//! Sqlx doesn't expose a way to create functions, so they are registered through `libsqlite3-sys` with the raw handle of the connection.
+//! See: <https://www.sqlite.org/c3ref/create_function.html>
+//!
+//! Note that the `A REGEXP B` operator is only available once a `regexp(B, A)` function is registered, with the pattern first.
+use core::cmp::Ordering;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use core::fmt::Debug;
+use std::ffi::CString;
+use std::panic::AssertUnwindSafe;
+use std::panic::catch_unwind;
+use std::sync::Arc;
+
+use libsqlite3_sys as ffi;
+use sqlx::SqliteConnection;
+use sqlx::sqlite::LockedSqliteHandle;
+
+/// A value passed to, or returned by a custom SQL function
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl SqlValue {
+    /// Return the value as text, if it is one
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Return the value as an integer, if it is one
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Self::Integer(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// Return the value as a float. Integers are converted
+    pub fn as_real(&self) -> Option<f64> {
+        match self {
+            Self::Real(val) => Some(*val),
+            Self::Integer(val) => Some(*val as f64),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+}
+
+impl From<i64> for SqlValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<bool> for SqlValue {
+    fn from(value: bool) -> Self {
+        Self::Integer(value.into())
+    }
+}
+
+impl From<f64> for SqlValue {
+    fn from(value: f64) -> Self {
+        Self::Real(value)
+    }
+}
+
+impl From<String> for SqlValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for SqlValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<Vec<u8>> for SqlValue {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Blob(value)
+    }
+}
+
+impl<T> From<Option<T>> for SqlValue
+where
+    T: Into<SqlValue>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Self::Null)
+    }
+}
+
+/// A custom aggregate function, like `SUM` or `GROUP_CONCAT`
+pub trait Aggregate: Send + Sync + 'static {
+    /// The running state of the aggregate for a group of rows
+    type State: Default;
+
+    /// Called on each row of the group
+    fn step(&self, state: &mut Self::State, args: &[SqlValue]) -> Result<(), String>;
+
+    /// Called once all the rows of the group have been processed. Return the result of the aggregate
+    fn finalize(&self, state: Self::State) -> Result<SqlValue, String>;
+}
+
+type RegisterFn = dyn Fn(&mut LockedSqliteHandle<'_>) -> Result<(), sqlx::Error> + Send + Sync;
+
+/// A set of custom functions and collations, to be registered on every new connection
+#[derive(Clone, Default)]
+pub struct SqliteFunctions {
+    names: Vec<Arc<str>>,
+    registrations: Vec<Arc<RegisterFn>>,
+}
+
+impl SqliteFunctions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a scalar function. `n_args` is the number of arguments the function takes, or -1 for any number.
+    ///
+    /// If `deterministic` is set, sqlite is allowed to reuse the result for identical arguments.
+    ///
+    /// Returning an error will make the query fail with the given message.
+    pub fn scalar<F>(self, name: &str, n_args: i32, deterministic: bool, function: F) -> Self
+    where
+        F: Fn(&[SqlValue]) -> Result<SqlValue, String> + Send + Sync + 'static,
+    {
+        let function = Arc::new(function);
+        let c_name = CString::new(name);
+
+        self.push(name, move |handle| {
+            let c_name = c_name.clone().map_err(invalid_name)?;
+
+            // SAFETY: The user data is an owned `Arc<F>`, released by `drop_arc::<F>`
+            let code = unsafe {
+                ffi::sqlite3_create_function_v2(
+                    handle.as_raw_handle().as_ptr(),
+                    c_name.as_ptr(),
+                    n_args,
+                    function_flags(deterministic),
+                    Arc::into_raw(function.clone()) as *mut c_void,
+                    Some(call_scalar::<F>),
+                    None,
+                    None,
+                    Some(drop_arc::<F>),
+                )
+            };
+
+            check_code(code, &c_name)
+        })
+    }
+
+    /// Add an aggregate function. `n_args` is the number of arguments the function takes, or -1 for any number.
+    pub fn aggregate<A>(self, name: &str, n_args: i32, deterministic: bool, aggregate: A) -> Self
+    where
+        A: Aggregate,
+    {
+        let aggregate = Arc::new(aggregate);
+        let c_name = CString::new(name);
+
+        self.push(name, move |handle| {
+            let c_name = c_name.clone().map_err(invalid_name)?;
+
+            // SAFETY: The user data is an owned `Arc<A>`, released by `drop_arc::<A>`
+            let code = unsafe {
+                ffi::sqlite3_create_function_v2(
+                    handle.as_raw_handle().as_ptr(),
+                    c_name.as_ptr(),
+                    n_args,
+                    function_flags(deterministic),
+                    Arc::into_raw(aggregate.clone()) as *mut c_void,
+                    None,
+                    Some(call_aggregate_step::<A>),
+                    Some(call_aggregate_final::<A>),
+                    Some(drop_arc::<A>),
+                )
+            };
+
+            check_code(code, &c_name)
+        })
+    }
+
+    /// Add a collation, usable with `ORDER BY column COLLATE name`.
+    ///
+    /// See [`sqlx::sqlite::SqliteConnectOptions::collation`] for the properties the function must uphold
+    pub fn collation<F>(self, name: &str, compare: F) -> Self
+    where
+        F: Fn(&str, &str) -> Ordering + Send + Sync + 'static,
+    {
+        let compare = Arc::new(compare);
+        let collation_name = name.to_string();
+
+        self.push(name, move |handle| {
+            let compare = compare.clone();
+            handle.create_collation(&collation_name, move |a, b| compare(a, b))
+        })
+    }
+
+    /// Register all the functions on a connection
+    pub async fn register(&self, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let mut handle = conn.lock_handle().await?;
+        for registration in &self.registrations {
+            registration(&mut handle)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registrations.is_empty()
+    }
+
+    fn push<F>(mut self, name: &str, registration: F) -> Self
+    where
+        F: Fn(&mut LockedSqliteHandle<'_>) -> Result<(), sqlx::Error> + Send + Sync + 'static,
+    {
+        self.names.push(name.into());
+        self.registrations.push(Arc::new(registration));
+        self
+    }
+}
+
+impl Debug for SqliteFunctions {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("SqliteFunctions").field(&self.names).finish()
+    }
+}
+
+fn function_flags(deterministic: bool) -> c_int {
+    if deterministic {
+        ffi::SQLITE_UTF8 | ffi::SQLITE_DETERMINISTIC
+    } else {
+        ffi::SQLITE_UTF8
+    }
+}
+
+fn check_code(code: c_int, name: &CString) -> Result<(), sqlx::Error> {
+    if code == ffi::SQLITE_OK {
+        return Ok(());
+    }
+
+    Err(sqlx::Error::Configuration(
+        format!(
+            "Could not register the function `{}` (code: {code})",
+            name.to_string_lossy()
+        )
+        .into(),
+    ))
+}
+
+fn invalid_name(err: std::ffi::NulError) -> sqlx::Error {
+    sqlx::Error::Configuration(format!("Invalid function name: {err}").into())
+}
+
+unsafe extern "C" fn drop_arc<T>(ptr: *mut c_void) {
+    // SAFETY: The pointer has been created by `Arc::into_raw` during registration
+    unsafe { drop(Arc::from_raw(ptr as *const T)) }
+}
+
+unsafe extern "C" fn call_scalar<F>(
+    ctx: *mut ffi::sqlite3_context,
+    n_args: c_int,
+    args: *mut *mut ffi::sqlite3_value,
+) where
+    F: Fn(&[SqlValue]) -> Result<SqlValue, String>,
+{
+    // SAFETY: sqlite gives back the user data we registered, and `n_args` valid values
+    unsafe {
+        let function = &*(ffi::sqlite3_user_data(ctx) as *const F);
+        let args = read_args(n_args, args);
+
+        let result = catch_unwind(AssertUnwindSafe(|| function(&args)));
+        set_result(ctx, result);
+    }
+}
+
+unsafe extern "C" fn call_aggregate_step<A>(
+    ctx: *mut ffi::sqlite3_context,
+    n_args: c_int,
+    args: *mut *mut ffi::sqlite3_value,
+) where
+    A: Aggregate,
+{
+    // SAFETY: sqlite gives back the user data we registered, and a zeroed state slot the size of a pointer on first call
+    unsafe {
+        let aggregate = &*(ffi::sqlite3_user_data(ctx) as *const A);
+        let slot = ffi::sqlite3_aggregate_context(ctx, size_of::<*mut A::State>() as c_int)
+            as *mut *mut A::State;
+
+        if slot.is_null() {
+            ffi::sqlite3_result_error_nomem(ctx);
+            return;
+        }
+
+        if (*slot).is_null() {
+            *slot = Box::into_raw(Box::default());
+        }
+
+        let state = &mut **slot;
+        let args = read_args(n_args, args);
+
+        match catch_unwind(AssertUnwindSafe(|| aggregate.step(state, &args))) {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => set_error(ctx, &err),
+            Err(_) => set_error(ctx, "The aggregate function panicked"),
+        }
+    }
+}
+
+unsafe extern "C" fn call_aggregate_final<A>(ctx: *mut ffi::sqlite3_context)
+where
+    A: Aggregate,
+{
+    // SAFETY: The slot is either null if `step` was never called, or contains the state set by `step`
+    unsafe {
+        let aggregate = &*(ffi::sqlite3_user_data(ctx) as *const A);
+        let slot = ffi::sqlite3_aggregate_context(ctx, 0) as *mut *mut A::State;
+
+        let state = if slot.is_null() || (*slot).is_null() {
+            A::State::default()
+        } else {
+            *Box::from_raw(core::mem::replace(&mut *slot, core::ptr::null_mut()))
+        };
+
+        let result = catch_unwind(AssertUnwindSafe(|| aggregate.finalize(state)));
+        set_result(ctx, result);
+    }
+}
+
+unsafe fn read_args(n_args: c_int, args: *mut *mut ffi::sqlite3_value) -> Vec<SqlValue> {
+    (0..n_args as usize)
+        // SAFETY: sqlite gives `n_args` values
+        .map(|i| unsafe { read_value(*args.add(i)) })
+        .collect()
+}
+
+unsafe fn read_value(value: *mut ffi::sqlite3_value) -> SqlValue {
+    // SAFETY: The value is valid for the duration of the call, and we copy its content out
+    unsafe {
+        match ffi::sqlite3_value_type(value) {
+            ffi::SQLITE_INTEGER => SqlValue::Integer(ffi::sqlite3_value_int64(value)),
+            ffi::SQLITE_FLOAT => SqlValue::Real(ffi::sqlite3_value_double(value)),
+            ffi::SQLITE_TEXT => {
+                let ptr = ffi::sqlite3_value_text(value);
+                let len = ffi::sqlite3_value_bytes(value) as usize;
+
+                if ptr.is_null() {
+                    SqlValue::Text(String::new())
+                } else {
+                    let bytes = core::slice::from_raw_parts(ptr, len);
+                    SqlValue::Text(String::from_utf8_lossy(bytes).into_owned())
+                }
+            }
+            ffi::SQLITE_BLOB => {
+                let ptr = ffi::sqlite3_value_blob(value) as *const u8;
+                let len = ffi::sqlite3_value_bytes(value) as usize;
+
+                if ptr.is_null() {
+                    SqlValue::Blob(Vec::new())
+                } else {
+                    SqlValue::Blob(core::slice::from_raw_parts(ptr, len).to_vec())
+                }
+            }
+            _ => SqlValue::Null,
+        }
+    }
+}
+
+unsafe fn set_result(
+    ctx: *mut ffi::sqlite3_context,
+    result: std::thread::Result<Result<SqlValue, String>>,
+) {
+    // SAFETY: `SQLITE_TRANSIENT` makes sqlite copy the data before we drop it
+    unsafe {
+        match result {
+            Ok(Ok(SqlValue::Null)) => ffi::sqlite3_result_null(ctx),
+            Ok(Ok(SqlValue::Integer(val))) => ffi::sqlite3_result_int64(ctx, val),
+            Ok(Ok(SqlValue::Real(val))) => ffi::sqlite3_result_double(ctx, val),
+            Ok(Ok(SqlValue::Text(val))) => ffi::sqlite3_result_text(
+                ctx,
+                val.as_ptr() as *const _,
+                val.len() as c_int,
+                ffi::SQLITE_TRANSIENT(),
+            ),
+            Ok(Ok(SqlValue::Blob(val))) => ffi::sqlite3_result_blob(
+                ctx,
+                val.as_ptr() as *const c_void,
+                val.len() as c_int,
+                ffi::SQLITE_TRANSIENT(),
+            ),
+            Ok(Err(err)) => set_error(ctx, &err),
+            Err(_) => set_error(ctx, "The function panicked"),
+        }
+    }
+}
+
+unsafe fn set_error(ctx: *mut ffi::sqlite3_context, message: &str) {
+    // SAFETY: sqlite copies the message
+    unsafe { ffi::sqlite3_result_error(ctx, message.as_ptr() as *const _, message.len() as c_int) }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::ConnectOptions as _;
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    use crate::databases::sqlite::functions::Aggregate;
+    use crate::databases::sqlite::functions::SqlValue;
+    use crate::databases::sqlite::functions::SqliteFunctions;
+
+    struct TotalLength;
+
+    impl Aggregate for TotalLength {
+        type State = i64;
+
+        fn step(&self, state: &mut Self::State, args: &[SqlValue]) -> Result<(), String> {
+            *state += args[0].as_text().ok_or("Expected some text")?.len() as i64;
+            Ok(())
+        }
+
+        fn finalize(&self, state: Self::State) -> Result<SqlValue, String> {
+            Ok(state.into())
+        }
+    }
+
+    #[tokio::test]
+    async fn functions_test() {
+        let functions = SqliteFunctions::new()
+            .scalar("casefold", 1, true, |args| {
+                Ok(args[0].as_text().map(str::to_lowercase).into())
+            })
+            .aggregate("total_length", 1, true, TotalLength)
+            .collation("reverse", |a, b| b.cmp(a));
+
+        let mut conn = SqliteConnectOptions::new()
+            .in_memory(true)
+            .connect()
+            .await
+            .unwrap();
+        functions.register(&mut conn).await.unwrap();
+
+        let folded: String = sqlx::query_scalar("SELECT casefold('HeLLo')")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(folded, "hello");
+
+        let total: i64 =
+            sqlx::query_scalar("SELECT total_length(x) FROM (SELECT 'ab' AS x UNION SELECT 'cde')")
+                .fetch_one(&mut conn)
+                .await
+                .unwrap();
+        assert_eq!(total, 5);
+
+        let err = sqlx::query_scalar::<_, i64>("SELECT total_length(1)")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Expected some text"));
+
+        let sorted: Vec<String> = sqlx::query_scalar(
+            "SELECT x FROM (SELECT 'a' AS x UNION SELECT 'b' UNION SELECT 'c') ORDER BY x COLLATE reverse",
+        )
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(sorted, ["c", "b", "a"]);
+    }
+}
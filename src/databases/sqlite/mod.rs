@@ -1,3 +1,5 @@
 pub mod connection;
 pub mod database;
+pub mod error;
+pub mod functions;
 pub mod pool;
@@ -5,19 +5,33 @@ use sqlx::Connection as _;
 use sqlx::SqliteConnection;
 use sqlx::sqlite::SqliteConnectOptions;
 
+use crate::databases::sqlite::functions::SqliteFunctions;
+
 /// A [deadpool] manager for an sqlite database
 #[derive(Debug)]
 pub struct SqlitePoolManager {
     config: SqliteConnectOptions,
+
+    /// Custom functions installed on each new connection
+    functions: SqliteFunctions,
 }
 
 impl SqlitePoolManager {
     pub fn new(config: SqliteConnectOptions) -> Self {
-        Self { config }
+        Self {
+            config,
+            functions: SqliteFunctions::default(),
+        }
+    }
+
+    /// Set the custom functions and collations to install on each new connection
+    pub fn with_functions(mut self, functions: SqliteFunctions) -> Self {
+        self.functions = functions;
+        self
     }
 
     pub fn create_pool(config: SqliteConnectOptions) -> SqlitePool {
-        SqlitePool::builder(SqlitePoolManager { config })
+        SqlitePool::builder(SqlitePoolManager::new(config))
             .build()
             .unwrap()
     }
@@ -28,7 +42,9 @@ impl managed::Manager for SqlitePoolManager {
     type Error = sqlx::Error;
 
     async fn create(&self) -> Result<Self::Type, Self::Error> {
-        SqliteConnection::connect_with(&self.config).await
+        let mut conn = SqliteConnection::connect_with(&self.config).await?;
+        self.functions.register(&mut conn).await?;
+        Ok(conn)
     }
 
     async fn recycle(
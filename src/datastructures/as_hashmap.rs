@@ -1,13 +1,68 @@
 use std::collections::HashMap;
 
+use crate::datastructures::hasher::BuildRowIdHasher;
+
 /// Return a reference to the inner hashmap.
-pub trait AsHashMap<K, V> {
+///
+/// It is implemented by the containers of the crate that keep their data in an [`HashMap`],
+/// so generic code can work with any of them.
+///
+/// # Exemple
+/// ```
+/// # use std::collections::HashMap;
+/// # use sequelles::RowIDMap;
+/// # use sequelles::Table;
+/// # use sequelles::datastructures::as_hashmap::AsHashMap;
+/// fn has_rowid<M, V>(container: &M, rowid: i64) -> bool
+/// where
+///     M: AsHashMap<i64, V>,
+/// {
+///     container.as_hashmap().contains_key(&rowid)
+/// }
+///
+/// let table: Table<i64> = Table::from(vec![1, 2]);
+/// let map: RowIDMap<i64, &str> = [(3, "c")].into_iter().collect();
+///
+/// assert!(has_rowid(&table, 2));
+/// assert!(has_rowid(&map, 3));
+/// ```
+pub trait AsHashMap<K, V, H = BuildRowIdHasher> {
     /// Return a reference to the inner hashmap.
-    fn as_hashmap(&self) -> HashMap<K, V>;
+    fn as_hashmap(&self) -> &HashMap<K, V, H>;
 }
 
 /// Return a mutable reference to the inner hashmap.
-pub trait AsHashMapMut<K, V> {
+///
+/// The values must be kept at the key they return, or the container won't find them anymore.
+pub trait AsHashMapMut<K, V, H = BuildRowIdHasher>: AsHashMap<K, V, H> {
     /// Return a mutable reference to the inner hashmap.
-    fn as_hashmap_mut(&self) -> HashMap<K, V>;
+    fn as_hashmap_mut(&mut self) -> &mut HashMap<K, V, H>;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RowIDMap;
+    use crate::datastructures::as_hashmap::AsHashMap;
+    use crate::datastructures::as_hashmap::AsHashMapMut;
+    use crate::datastructures::joins::many_to_zero_join::ManyToZeroJoin;
+    use crate::datastructures::joins::zero_to_many_join::ZeroToManyJoin;
+
+    #[test]
+    fn as_hashmap_mut_test() {
+        let mut map: RowIDMap<i64, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+        map.as_hashmap_mut().get_mut(&1).unwrap().1 = "c";
+        map.as_hashmap_mut().remove(&2);
+        assert_eq!(map.get(&1), Some(&"c"));
+        assert_eq!(map.as_hashmap().len(), 1);
+
+        let mut join: ZeroToManyJoin<i64, &str> = ZeroToManyJoin::default();
+        join.insert(Some(1), vec!["a"]);
+        join.as_hashmap_mut().get_mut(&1).unwrap().1.push("b");
+        assert_eq!(join.as_hashmap()[&1].1, vec!["a", "b"]);
+
+        let mut join: ManyToZeroJoin<i64, &str> = ManyToZeroJoin::default();
+        join.insert(1, None);
+        join.as_hashmap_mut().get_mut(&1).unwrap().1 = Some("a");
+        assert_eq!(join.as_hashmap()[&1], (1, Some("a")));
+    }
 }
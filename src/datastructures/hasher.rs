@@ -0,0 +1,84 @@
+use core::hash::BuildHasherDefault;
+use core::hash::Hasher;
+
+/// The [`BuildHasher`](core::hash::BuildHasher) of [`RowIdHasher`]. This is the default hasher of the rowid keyed maps of the crate
+pub type BuildRowIdHasher = BuildHasherDefault<RowIdHasher>;
+
+/// A fast, non cryptographic hasher for rowids.
+///
+/// A rowid is hashed with a single multiplication. Returning it as is would be faster, but [`HashMap`](std::collections::HashMap)
+/// relies on the top bits of the hash, which are always zero for small integers. Other keys are hashed 8 bytes at a time.
+///
+/// It isn't resistant to HashDoS. Use [`RandomState`](std::hash::RandomState) as the hasher parameter if the keys come from an untrusted source,
+/// like `KeyedTable<R, RandomState>`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RowIdHasher(u64);
+
+/// 2^64 divided by the golden ratio, which spreads consecutive integers across the whole range
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+impl RowIdHasher {
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for RowIdHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(i.into());
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add(i.into());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add(i.into());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.add(i as u64);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::hash::BuildHasher;
+    use std::collections::HashSet;
+
+    use crate::datastructures::hasher::BuildRowIdHasher;
+
+    #[test]
+    fn rowid_hasher_test() {
+        let hasher = BuildRowIdHasher::default();
+
+        // HashMap uses the top 7 bits as a tag, so consecutive rowids must not share them
+        let tags = (1..=128_i64)
+            .map(|rowid| hasher.hash_one(rowid) >> 57)
+            .collect::<HashSet<_>>();
+        assert!(tags.len() > 64);
+
+        assert_eq!(hasher.hash_one(42_i64), hasher.hash_one(42_i64));
+        assert_ne!(hasher.hash_one("ab"), hasher.hash_one("ba"));
+    }
+}
@@ -1,27 +1,81 @@
+use core::hash::BuildHasher;
+use core::hash::Hash;
+
+use futures::Stream;
+use futures::TryStreamExt as _;
+use sqlx::Executor;
+use sqlx::FromRow;
+use sqlx::IntoArguments;
+use sqlx::query::QueryAs;
+
 use crate::JoinRelation;
+use crate::KeyedManyToZeroJoin;
 use crate::ManyToManyJoin;
 use crate::ManyToZeroJoin;
-use crate::has_rowid::HasRowID;
+use crate::datastructures::key_set::KeySet;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::ByKey;
+use crate::has_key::HasKey;
 
 /// A collection of [`JoinRelation`]
+///
+/// The relations point to their left element with their `original_id` column, of type `K`.
+/// It is a rowid by default, but it can be any [`HasKey::Key`] of the left elements
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
-pub struct JoinCollection<T> {
-    joins: Vec<JoinRelation<T>>,
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct JoinCollection<T, K = i64> {
+    joins: Vec<JoinRelation<T, (), K>>,
 }
 
-impl<R> JoinCollection<R> {
-    pub fn joins(&self) -> &Vec<JoinRelation<R>> {
+impl<R, K> JoinCollection<R, K> {
+    pub fn joins(&self) -> &Vec<JoinRelation<R, (), K>> {
         &self.joins
     }
 
-    pub fn joins_mut(&mut self) -> &mut Vec<JoinRelation<R>> {
+    pub fn joins_mut(&mut self) -> &mut Vec<JoinRelation<R, (), K>> {
         &mut self.joins
     }
 
-    pub fn push(&mut self, join: JoinRelation<R>) {
+    pub fn push(&mut self, join: JoinRelation<R, (), K>) {
         self.joins.push(join);
     }
 
+    /// Run the query and push the relations into a new collection, as they are received
+    pub async fn fetch<'q, 'e, 'c: 'e, DB, A, E>(
+        query: QueryAs<'q, DB, JoinRelation<R, (), K>, A>,
+        executor: E,
+    ) -> Result<Self, sqlx::Error>
+    where
+        DB: sqlx::Database,
+        JoinRelation<R, (), K>: 'e + Send + Unpin + for<'r> FromRow<'r, DB::Row>,
+        A: 'q + IntoArguments<'q, DB>,
+        E: 'e + Executor<'c, Database = DB>,
+        'q: 'e,
+        DB: 'e,
+        A: 'e,
+    {
+        Self::try_from_stream(query.fetch(executor)).await
+    }
+
+    /// Push the relations of a stream into a new collection, as they are received
+    pub async fn try_from_stream<St, E>(stream: St) -> Result<Self, E>
+    where
+        St: Stream<Item = Result<JoinRelation<R, (), K>, E>>,
+    {
+        let mut stream = core::pin::pin!(stream);
+        let mut joins = Self::from(Vec::new());
+
+        while let Some(join) = stream.try_next().await? {
+            joins.push(join);
+        }
+
+        Ok(joins)
+    }
+
     /// See [`Vec::len`]
     pub fn len(&self) -> usize {
         self.joins.len()
@@ -36,10 +90,22 @@ impl<R> JoinCollection<R> {
     ///
     /// This takes in a vec of the left values, then associate the right values contained in the [`JoinRelation`],
     /// using the [`JoinRelation::external_id`] field
-    pub fn into_many_to_zero<L, T>(self, left_elements: T) -> ManyToZeroJoin<L, R>
+    pub fn into_many_to_zero<L, T>(self, left_elements: T) -> KeyedManyToZeroJoin<L, R>
+    where
+        K: Eq + Hash,
+        L: HasKey<Key = K>,
+        T: IntoIterator<Item = L>,
+    {
+        self.into_many_to_zero_in(left_elements)
+    }
+
+    /// Same as [`JoinCollection::into_many_to_zero`], but the join use a custom [`RowStorage`],
+    /// like [`DenseStorage`](crate::datastructures::storage::dense::DenseStorage)
+    pub fn into_many_to_zero_in<S, L, T>(self, left_elements: T) -> ManyToZeroJoin<L, R, S>
     where
-        L: HasRowID,
+        L: HasKey<Key = K>,
         T: IntoIterator<Item = L>,
+        S: RowStorage<(L, Option<R>), Key = K>,
     {
         let mut smart_join = ManyToZeroJoin::default();
 
@@ -50,17 +116,25 @@ impl<R> JoinCollection<R> {
 
         // Now add the right values
         for (l_id, right) in self.joins.into_iter().map(|join| join.into_tuple()) {
-            smart_join.replace_by_id(l_id, right);
+            smart_join.replace_by_id(ByKey(l_id), right);
         }
 
         smart_join
     }
 
-    pub fn into_many_to_many<L, T>(self, left_elements: T) -> ManyToManyJoin<L, R>
+    /// Convert the join relations into a [`ManyToManyJoin`], with any hasher and [`KeySet`]s.
+    /// For rowids, this is usually a plain `ManyToManyJoin<L, R>`
+    pub fn into_many_to_many<L, T, H, LS, RS>(
+        self,
+        left_elements: T,
+    ) -> ManyToManyJoin<L, R, H, LS, RS>
     where
-        L: HasRowID,
-        R: HasRowID,
+        L: HasKey<Key = K>,
+        R: HasKey,
         T: IntoIterator<Item = L>,
+        H: BuildHasher + Default,
+        LS: KeySet<K>,
+        RS: KeySet<R::Key>,
     {
         let mut smart_join = ManyToManyJoin::default();
 
@@ -70,7 +144,7 @@ impl<R> JoinCollection<R> {
         }
 
         for (l_id, right) in self.joins.into_iter().map(|join| join.into_tuple()) {
-            smart_join.add_relation_ids(l_id, right.rowid());
+            smart_join.add_relation_ids(ByKey(l_id), ByKey(right.key()));
             smart_join.add_right(right);
         }
 
@@ -78,8 +152,84 @@ impl<R> JoinCollection<R> {
     }
 }
 
-impl<T> From<Vec<JoinRelation<T>>> for JoinCollection<T> {
-    fn from(value: Vec<JoinRelation<T>>) -> Self {
+impl<T, K> From<Vec<JoinRelation<T, (), K>>> for JoinCollection<T, K> {
+    fn from(value: Vec<JoinRelation<T, (), K>>) -> Self {
         Self { joins: value }
     }
 }
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+    use sqlx::FromRow;
+    use sqlx::SqlitePool;
+
+    use crate::JoinCollection;
+    use crate::JoinRelation;
+    use crate::KeyedManyToManyJoin;
+    use crate::has_key::ByKey;
+    use crate::has_key::HasKey;
+
+    #[derive(Debug, FromRow)]
+    struct Recording {
+        id: i64,
+        title: String,
+    }
+
+    #[tokio::test]
+    async fn fetch_test() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql("CREATE TABLE recordings (id INTEGER PRIMARY KEY, title TEXT NOT NULL, artist_id INTEGER NOT NULL); INSERT INTO recordings VALUES (1, 'a', 10), (2, 'b', 10), (3, 'c', 20);")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let joins: JoinCollection<Recording> = JoinCollection::fetch(
+            sqlx::query_as(
+                "SELECT id, title, artist_id AS original_id FROM recordings ORDER BY id",
+            ),
+            &pool,
+        )
+        .await
+        .unwrap();
+        assert_eq!(joins.len(), 3);
+        assert_eq!(joins.joins()[2].original_id, 20);
+        assert_eq!(joins.joins()[2].data.title, "c");
+        assert_eq!(joins.joins()[0].data.id, 1);
+
+        let missing: Result<JoinCollection<Recording>, _> =
+            JoinCollection::fetch(sqlx::query_as("SELECT * FROM recordings"), &pool).await;
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn keyed_test() {
+        struct Artist {
+            name: &'static str,
+        }
+
+        impl HasKey for Artist {
+            type Key = &'static str;
+
+            fn key(&self) -> Self::Key {
+                self.name
+            }
+        }
+
+        let joins: JoinCollection<i64, &str> = JoinCollection::from(vec![
+            JoinRelation::new(1, "Mitski"),
+            JoinRelation::new(2, "Mitski"),
+            JoinRelation::new(3, "Björk"),
+        ]);
+        let artists = || [Artist { name: "Mitski" }, Artist { name: "Björk" }];
+
+        let many_to_many: KeyedManyToManyJoin<Artist, i64> =
+            joins.clone().into_many_to_many(artists());
+        assert_eq!(
+            many_to_many.get_associated_rights_by_id(ByKey("Mitski")),
+            [&1, &2]
+        );
+
+        let many_to_zero = joins.into_many_to_zero(artists());
+        assert!(many_to_zero.get_by_id(ByKey("Björk")).unwrap().is_some());
+    }
+}
@@ -1,25 +1,113 @@
+use core::fmt;
+use core::hash::Hash;
+use core::hash::Hasher;
+use core::marker::PhantomData;
+
 use sqlx::FromRow;
 
+use crate::RowId;
+
 /// Represent a row of a table, with an associated external table rowid.
 ///
-/// For exemple: the underlying data is a row from the `recordings` table, and the associated id is the id of artist of the recording
-#[derive(Clone, PartialEq, Eq, Hash, Debug, FromRow)]
-pub struct JoinRelation<T> {
+/// For exemple: the underlying data is a row from the `recordings` table, and the associated id is the id of artist of the recording.
+///
+/// `L` is the type of the external row, used to type [`JoinRelation::original_row_id`]. It is `()` when not needed.
+/// `K` is the type of the external id, for external rows indexed by a [`HasKey::Key`](crate::has_key::HasKey::Key) instead of a rowid
+///
+/// ```compile_fail
+/// # use sequelles::JoinRelation;
+/// # use sequelles::RowId;
+/// struct Artist;
+/// struct Label;
+///
+/// let relation: JoinRelation<&str, Artist> = JoinRelation::new("recording", 1);
+/// let label_id: RowId<Label> = relation.original_row_id();
+/// ```
+#[derive(FromRow)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JoinRelation<T, L = (), K = i64> {
     /// The id of the external row associated to it
-    pub original_id: i64,
+    pub original_id: K,
 
     /// The table's row
     #[sqlx(flatten)]
     pub data: T,
+
+    #[sqlx(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _left: PhantomData<fn() -> L>,
 }
 
-impl<T> JoinRelation<T> {
-    pub fn new(data: T, original_id: i64) -> Self {
-        Self { data, original_id }
+impl<T, L, K> JoinRelation<T, L, K> {
+    pub fn new(data: T, original_id: K) -> Self {
+        Self {
+            data,
+            original_id,
+            _left: PhantomData,
+        }
     }
 
     /// Convert the join relation into a tuple
-    pub fn into_tuple(self) -> (i64, T) {
+    pub fn into_tuple(self) -> (K, T) {
         (self.original_id, self.data)
     }
 }
+
+impl<T, L> JoinRelation<T, L> {
+    /// The id of the external row, typed after it
+    pub fn original_row_id(&self) -> RowId<L> {
+        RowId::new(self.original_id)
+    }
+}
+
+// Manual impls, as deriving would require the bounds on `L`
+impl<T, L, K> Clone for JoinRelation<T, L, K>
+where
+    T: Clone,
+    K: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.data.clone(), self.original_id.clone())
+    }
+}
+
+impl<T, L, K> PartialEq for JoinRelation<T, L, K>
+where
+    T: PartialEq,
+    K: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.original_id == other.original_id && self.data == other.data
+    }
+}
+
+impl<T, L, K> Eq for JoinRelation<T, L, K>
+where
+    T: Eq,
+    K: Eq,
+{
+}
+
+impl<T, L, K> Hash for JoinRelation<T, L, K>
+where
+    T: Hash,
+    K: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.original_id.hash(state);
+        self.data.hash(state);
+    }
+}
+
+impl<T, L, K> fmt::Debug for JoinRelation<T, L, K>
+where
+    T: fmt::Debug,
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinRelation")
+            .field("original_id", &self.original_id)
+            .field("data", &self.data)
+            .finish()
+    }
+}
@@ -1,19 +1,28 @@
+use core::hash::BuildHasher;
+
 use crate::ManyToManyJoin;
-use crate::has_rowid::HasRowID;
+use crate::datastructures::key_set::KeySet;
+use crate::has_key::ByKey;
+use crate::has_key::HasKey;
 
-impl<L, R> ManyToManyJoin<L, R>
+impl<L, R, H, LS, RS> ManyToManyJoin<L, R, H, LS, RS>
 where
-    L: HasRowID,
-    R: HasRowID,
+    L: HasKey,
+    R: HasKey,
+    H: BuildHasher + Default,
+    LS: KeySet<L::Key>,
+    RS: KeySet<R::Key>,
 {
     pub fn relations(&self) -> Vec<(&L, &R)> {
         let mut relations = Vec::with_capacity(self.left_table.len() + self.right_table.len());
 
         for (left, rights) in &self.left_to_right {
-            for right in rights {
+            for right in rights.iter() {
                 relations.push((
-                    self.get_left(left).expect("Id should be in the map"),
-                    self.get_right(right).expect("Id should be in the map"),
+                    self.get_left(ByKey(left.clone()))
+                        .expect("Id should be in the map"),
+                    self.get_right(ByKey(right))
+                        .expect("Id should be in the map"),
                 ));
             }
         }
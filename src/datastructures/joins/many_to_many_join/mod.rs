@@ -1,7 +1,16 @@
+use core::hash::BuildHasher;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 
+use crate::RowIdSet;
 use crate::Table;
 use crate::ZeroToManyJoin;
+use crate::datastructures::as_hashmap::AsHashMap;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::key_set::KeySet;
+use crate::has_key::ByKey;
+use crate::has_key::HasKey;
+use crate::has_key::IntoKey;
 use crate::has_rowid::HasRowID;
 
 pub mod iterator;
@@ -12,18 +21,41 @@ pub mod iterator;
 ///
 /// # Exemple
 /// An artist can have many (0:N) recordings, but a recording can have many (0:N) artists.
-pub struct ManyToManyJoin<L, R> {
-    left_table: Table<L>,
-    right_table: Table<R>,
+///
+/// All the inner maps use the hasher `H`.
+///
+/// The keys of the related rows are held in the [`KeySet`] `LS` for the left rows, and `RS` for the right rows.
+/// They are [`RowIdSet`] by default, so rows keyed by something else than a rowid should use a [`KeyedManyToManyJoin`].
+/// As they are sets, a relation is only held once, and the related rows are given in key order.
+pub struct ManyToManyJoin<L, R, H = BuildRowIdHasher, LS = RowIdSet, RS = RowIdSet>
+where
+    L: HasKey,
+    R: HasKey,
+{
+    left_table: JoinTable<L, H>,
+    right_table: JoinTable<R, H>,
 
-    left_to_right: HashMap<i64, Vec<i64>>,
-    right_to_left: HashMap<i64, Vec<i64>>,
+    left_to_right: Relations<L, RS, H>,
+    right_to_left: Relations<R, LS, H>,
 }
 
-impl<L, R> ManyToManyJoin<L, R>
+/// A [`ManyToManyJoin`] of rows indexed by a key other than a rowid, holding the related keys in [`BTreeSet`]s
+pub type KeyedManyToManyJoin<L, R, H = BuildRowIdHasher> =
+    ManyToManyJoin<L, R, H, BTreeSet<<L as HasKey>::Key>, BTreeSet<<R as HasKey>::Key>>;
+
+/// A table of the join, using the hasher of the join
+type JoinTable<T, H> = Table<T, HashMap<<T as HasKey>::Key, T, H>>;
+
+/// The set `S` of keys related to each `A` row
+type Relations<A, S, H> = HashMap<<A as HasKey>::Key, S, H>;
+
+impl<L, R, H, LS, RS> ManyToManyJoin<L, R, H, LS, RS>
 where
-    L: HasRowID,
-    R: HasRowID,
+    L: HasKey,
+    R: HasKey,
+    H: BuildHasher + Default,
+    LS: KeySet<L::Key>,
+    RS: KeySet<R::Key>,
 {
     /// Add a new element to the left table
     pub fn add_left(&mut self, left: L) {
@@ -35,50 +67,75 @@ where
         self.right_table.insert(right);
     }
 
-    /// Add a new relation between a left element and a right element using their rowids
-    pub fn add_relation_ids(&mut self, left: i64, right: i64) {
-        self.left_to_right.entry(left).or_default().push(right);
-        self.right_to_left.entry(right).or_default().push(left);
+    /// Add a new relation between a left element and a right element using their rowids, or keys.
+    ///
+    /// Adding an existing relation does nothing
+    pub fn add_relation_ids<IL, IR>(&mut self, left: IL, right: IR)
+    where
+        IL: IntoKey<L>,
+        IR: IntoKey<R>,
+    {
+        let (left, right) = (left.into_key(), right.into_key());
+        self.left_to_right
+            .entry(left.clone())
+            .or_default()
+            .insert(right.clone());
+        self.right_to_left.entry(right).or_default().insert(left);
     }
 
     /// Add a new relation between a left element and a right element
     pub fn add_relation(&mut self, left: &L, right: &R) {
-        self.add_relation_ids(left.rowid(), right.rowid());
+        self.add_relation_ids(ByKey(left.key()), ByKey(right.key()));
     }
 
-    /// Remove a relation between a left element and a right element using their rowids
-    pub fn remove_relation_ids(&mut self, left: i64, right: i64) {
-        if let Some(left_vec) = self.left_to_right.get_mut(&left) {
-            left_vec.retain(|&x| x != right);
+    /// Remove a relation between a left element and a right element using their rowids, or keys
+    pub fn remove_relation_ids<IL, IR>(&mut self, left: IL, right: IR)
+    where
+        IL: IntoKey<L>,
+        IR: IntoKey<R>,
+    {
+        let (left, right) = (left.into_key(), right.into_key());
+        if let Some(rights) = self.left_to_right.get_mut(&left) {
+            rights.remove(&right);
         }
-        if let Some(right_vec) = self.right_to_left.get_mut(&right) {
-            right_vec.retain(|&x| x != left);
+        if let Some(lefts) = self.right_to_left.get_mut(&right) {
+            lefts.remove(&left);
         }
     }
 
     /// Remove a relation between a left element and a right element
     pub fn remove_relation(&mut self, left: &L, right: &R) {
-        self.remove_relation_ids(left.rowid(), right.rowid());
+        self.remove_relation_ids(ByKey(left.key()), ByKey(right.key()));
     }
 
-    /// Get a left element by its rowid
-    pub fn get_left(&self, key: &i64) -> Option<&L> {
+    /// Get a left element by its rowid, or key
+    pub fn get_left<I>(&self, key: I) -> Option<&L>
+    where
+        I: IntoKey<L>,
+    {
         self.left_table.get(key)
     }
 
-    /// Get a right element by its rowid
-    pub fn get_right(&self, key: &i64) -> Option<&R> {
+    /// Get a right element by its rowid, or key
+    pub fn get_right<I>(&self, key: I) -> Option<&R>
+    where
+        I: IntoKey<R>,
+    {
         self.right_table.get(key)
     }
 
-    /// Get all associated right elements to a left element by its rowid
-    pub fn get_associated_rights_by_id(&self, left: i64) -> Vec<&R> {
+    /// Get all associated right elements to a left element by its rowid, or key. They are in key order
+    pub fn get_associated_rights_by_id<I>(&self, left: I) -> Vec<&R>
+    where
+        I: IntoKey<L>,
+    {
+        let left = left.into_key();
         self.left_to_right
             .get(&left)
             .map(|r_ids| {
                 r_ids
                     .iter()
-                    .filter_map(|id| self.right_table.get(id))
+                    .filter_map(|id| self.right_table.get(ByKey(id)))
                     .collect::<Vec<_>>()
             })
             .unwrap_or_default()
@@ -86,17 +143,21 @@ where
 
     /// Get all associated right elements to a left element
     pub fn get_associated_rights(&self, left: &L) -> Vec<&R> {
-        self.get_associated_rights_by_id(left.rowid())
+        self.get_associated_rights_by_id(ByKey(left.key()))
     }
 
-    /// Get all associated left elements to a right element by its rowid
-    pub fn get_associated_lefts_by_id(&self, right: i64) -> Vec<&L> {
+    /// Get all associated left elements to a right element by its rowid, or key. They are in key order
+    pub fn get_associated_lefts_by_id<I>(&self, right: I) -> Vec<&L>
+    where
+        I: IntoKey<R>,
+    {
+        let right = right.into_key();
         self.right_to_left
             .get(&right)
             .map(|l_ids| {
                 l_ids
                     .iter()
-                    .filter_map(|id| self.left_table.get(id))
+                    .filter_map(|id| self.left_table.get(ByKey(id)))
                     .collect::<Vec<_>>()
             })
             .unwrap_or_default()
@@ -104,9 +165,18 @@ where
 
     /// Get all associated left elements to a right element
     pub fn get_associated_lefts(&self, right: &R) -> Vec<&L> {
-        self.get_associated_lefts_by_id(right.rowid())
+        self.get_associated_lefts_by_id(ByKey(right.key()))
     }
+}
 
+impl<L, R, H, LS, RS> ManyToManyJoin<L, R, H, LS, RS>
+where
+    L: HasRowID,
+    R: HasRowID,
+    H: BuildHasher + Default,
+    LS: KeySet<i64>,
+    RS: KeySet<i64>,
+{
     pub fn into_many_to_zero_left(self) -> ZeroToManyJoin<L, R>
     where
         R: Clone,
@@ -122,14 +192,16 @@ where
     }
 }
 
-fn into_many_to_zero<L, R>(
-    left_table: Table<L>,
-    right_table: Table<R>,
-    right_to_left: HashMap<i64, Vec<i64>>,
+fn into_many_to_zero<L, R, H, S>(
+    left_table: JoinTable<L, H>,
+    right_table: JoinTable<R, H>,
+    right_to_left: Relations<R, S, H>,
 ) -> ZeroToManyJoin<L, R>
 where
+    H: BuildHasher + Default,
     L: HasRowID,
     R: HasRowID + Clone,
+    S: KeySet<i64>,
 {
     let mut new_map = ZeroToManyJoin::default();
 
@@ -139,30 +211,157 @@ where
     }
 
     for right in right_table {
-        let lefts = right_to_left
-            .get(&right.rowid())
-            .cloned()
-            .unwrap_or_default();
-
-        if lefts.is_empty() {
-            new_map.push_entry(None, right);
-        } else {
-            lefts
-                .into_iter()
-                .for_each(|left_id| new_map.push_right_by_id(left_id, right.clone()));
+        match right_to_left.get(&right.rowid()) {
+            Some(lefts) if !lefts.is_empty() => lefts
+                .iter()
+                .for_each(|left_id| new_map.push_right_by_id(left_id, right.clone())),
+            _ => new_map.push_entry(None, right),
         }
     }
 
     new_map
 }
 
-impl<L, R> Default for ManyToManyJoin<L, R> {
+/// The relations, from the left keys to the right keys. There is no mutable access, as the inverse index would get out of sync
+impl<L, R, H, LS, RS> AsHashMap<L::Key, RS, H> for ManyToManyJoin<L, R, H, LS, RS>
+where
+    L: HasKey,
+    R: HasKey,
+{
+    fn as_hashmap(&self) -> &Relations<L, RS, H> {
+        &self.left_to_right
+    }
+}
+
+impl<L, R, H, LS, RS> Default for ManyToManyJoin<L, R, H, LS, RS>
+where
+    L: HasKey,
+    R: HasKey,
+    H: Default,
+{
     fn default() -> Self {
         Self {
             left_table: Table::default(),
             right_table: Table::default(),
-            left_to_right: HashMap::new(),
-            right_to_left: HashMap::new(),
+            left_to_right: HashMap::default(),
+            right_to_left: HashMap::default(),
         }
     }
 }
+
+/// Serialized as the two tables, and the list of `(left_key, right_key)` relations
+#[cfg(feature = "serde")]
+impl<L, R, H, LS, RS> serde::Serialize for ManyToManyJoin<L, R, H, LS, RS>
+where
+    H: BuildHasher + Default,
+    RS: KeySet<R::Key>,
+    L: HasKey + serde::Serialize,
+    R: HasKey + serde::Serialize,
+    L::Key: serde::Serialize,
+    R::Key: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(serde::Serialize)]
+        struct SerializedJoin<'a, L, R, LK, RK> {
+            left: &'a L,
+            right: &'a R,
+            relations: Vec<(&'a LK, RK)>,
+        }
+
+        let mut relations = self
+            .left_to_right
+            .iter()
+            .flat_map(|(left, rights)| rights.iter().map(move |right| (left, right)))
+            .collect::<Vec<_>>();
+        relations.sort_unstable();
+
+        SerializedJoin {
+            left: &self.left_table,
+            right: &self.right_table,
+            relations,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The relation indexes are rebuilt from the relation list
+#[cfg(feature = "serde")]
+impl<'de, L, R, H, LS, RS> serde::Deserialize<'de> for ManyToManyJoin<L, R, H, LS, RS>
+where
+    H: BuildHasher + Default,
+    LS: KeySet<L::Key>,
+    RS: KeySet<R::Key>,
+    L: HasKey + serde::Deserialize<'de>,
+    R: HasKey + serde::Deserialize<'de>,
+    L::Key: serde::Deserialize<'de>,
+    R::Key: serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        struct SerializedJoin<L, R, LK, RK> {
+            left: L,
+            right: R,
+            relations: Vec<(LK, RK)>,
+        }
+
+        let serialized = SerializedJoin::deserialize(deserializer)?;
+        let mut join = Self {
+            left_table: serialized.left,
+            right_table: serialized.right,
+            ..Default::default()
+        };
+
+        for (left, right) in serialized.relations {
+            join.add_relation_ids(ByKey(left), ByKey(right));
+        }
+
+        Ok(join)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ManyToManyJoin;
+
+    #[test]
+    fn relation_set_test() {
+        let mut join = ManyToManyJoin::<i64, i64>::default();
+        join.add_left(1);
+        join.add_right(10);
+        join.add_right(20);
+
+        // Relations are held once, and given back in key order
+        join.add_relation_ids(1, 20);
+        join.add_relation_ids(1, 10);
+        join.add_relation_ids(1, 20);
+        assert_eq!(join.get_associated_rights_by_id(1), [&10, &20]);
+        assert_eq!(join.relations(), [(&1, &10), (&1, &20)]);
+
+        join.remove_relation_ids(1, 20);
+        assert_eq!(join.get_associated_rights_by_id(1), [&10]);
+        assert!(join.get_associated_lefts_by_id(20).is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip_test() {
+        let mut join = ManyToManyJoin::<i64, i64>::default();
+        join.add_left(1);
+        join.add_left(2);
+        join.add_right(10);
+        join.add_relation_ids(1, 10);
+        join.add_relation_ids(2, 10);
+
+        let json = serde_json::to_string(&join).unwrap();
+        let join: ManyToManyJoin<i64, i64> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(join.get_associated_rights_by_id(1), [&10]);
+        assert_eq!(join.get_associated_lefts_by_id(10).len(), 2);
+    }
+}
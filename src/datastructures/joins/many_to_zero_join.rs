@@ -1,34 +1,51 @@
 use core::ops::Deref;
 use core::ops::DerefMut;
 use std::collections::HashMap;
-use std::collections::hash_map::IntoValues;
 
 use crate::RowIDMap;
+use crate::datastructures::as_hashmap::AsHashMap;
+use crate::datastructures::as_hashmap::AsHashMapMut;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::joins::zero_to_many_join::KeyedZeroToManyJoin;
 use crate::datastructures::joins::zero_to_many_join::ZeroToManyJoin;
-use crate::has_rowid::HasRowID;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::HasKey;
+use crate::has_key::IntoKey;
 
 /// An [`crate::RowIDMap`] that represent a `LEFT JOIN`, where an element of the Left table <u>can</u> have <u>one</u> element of the Right table
 ///
 /// Example: **a Listen <u>can</u> have a Recording**, but a Recording can have <u>many</u> Listens
-pub struct ManyToZeroJoin<L, R>(pub(super) RowIDMap<L, Option<R>>);
+///
+/// Like [`RowIDMap`], the pairs can be kept in any [`RowStorage`].
+pub struct ManyToZeroJoin<L, R, S = HashMap<i64, (L, Option<R>), BuildRowIdHasher>>(
+    pub(super) RowIDMap<L, Option<R>, S>,
+);
+
+/// A [`ManyToZeroJoin`] with left elements indexed by a key other than a rowid. For rowids, this is the same type as [`ManyToZeroJoin`]
+pub type KeyedManyToZeroJoin<L, R, H = BuildRowIdHasher> =
+    ManyToZeroJoin<L, R, HashMap<<L as HasKey>::Key, (L, Option<R>), H>>;
 
-impl<L, R> ManyToZeroJoin<L, R>
+impl<L, R, S> ManyToZeroJoin<L, R, S>
 where
-    L: HasRowID,
+    L: HasKey,
+    S: RowStorage<(L, Option<R>), Key = L::Key>,
 {
     /// Insert a key-value pair
     pub fn insert(&mut self, left: L, right: Option<R>) {
         self.0.insert(left, right);
     }
 
-    /// Replace the value at a specific rowid
-    pub fn replace_by_id(&mut self, key: i64, value: R) -> Option<R> {
+    /// Replace the value at a specific rowid, or key
+    pub fn replace_by_id<I>(&mut self, key: I, value: R) -> Option<R>
+    where
+        I: IntoKey<L>,
+    {
         self.0.get_mut_by_id(key).and_then(|val| val.replace(value))
     }
 
-    pub fn invert(self) -> ZeroToManyJoin<R, L>
+    pub fn invert(self) -> KeyedZeroToManyJoin<R, L>
     where
-        R: HasRowID,
+        R: HasKey,
     {
         let mut new_map = ZeroToManyJoin::default();
 
@@ -39,15 +56,10 @@ where
         new_map
     }
 
-    /// Return the underlying hashmap.
-    pub fn as_mut_hashmap(&mut self) -> &mut HashMap<i64, (L, Option<R>)> {
-        self.0.as_mut_hash_map()
-    }
-
-    pub fn map_left<F, U>(self, f: F) -> ManyToZeroJoin<U, R>
+    pub fn map_left<F, U>(self, f: F) -> KeyedManyToZeroJoin<U, R>
     where
         F: Fn(L) -> U,
-        U: HasRowID,
+        U: HasKey,
     {
         let mut new_map = ManyToZeroJoin::default();
 
@@ -59,7 +71,7 @@ where
         new_map
     }
 
-    pub fn map_right<F, U>(self, f: F) -> ManyToZeroJoin<L, U>
+    pub fn map_right<F, U>(self, f: F) -> KeyedManyToZeroJoin<L, U>
     where
         F: Fn(R) -> U,
     {
@@ -74,31 +86,119 @@ where
     }
 }
 
-impl<L, R> Default for ManyToZeroJoin<L, R> {
+impl<L, R, H> ManyToZeroJoin<L, R, HashMap<L::Key, (L, Option<R>), H>>
+where
+    L: HasKey,
+{
+    #[deprecated(note = "use `AsHashMapMut::as_hashmap_mut` instead")]
+    pub fn as_mut_hashmap(&mut self) -> &mut HashMap<L::Key, (L, Option<R>), H> {
+        self.as_hashmap_mut()
+    }
+}
+
+impl<L, R, H> AsHashMap<L::Key, (L, Option<R>), H>
+    for ManyToZeroJoin<L, R, HashMap<L::Key, (L, Option<R>), H>>
+where
+    L: HasKey,
+{
+    fn as_hashmap(&self) -> &HashMap<L::Key, (L, Option<R>), H> {
+        self.0.as_hashmap()
+    }
+}
+
+impl<L, R, H> AsHashMapMut<L::Key, (L, Option<R>), H>
+    for ManyToZeroJoin<L, R, HashMap<L::Key, (L, Option<R>), H>>
+where
+    L: HasKey,
+{
+    fn as_hashmap_mut(&mut self) -> &mut HashMap<L::Key, (L, Option<R>), H> {
+        self.0.as_hashmap_mut()
+    }
+}
+
+impl<L, R, S> Default for ManyToZeroJoin<L, R, S>
+where
+    S: Default,
+{
     fn default() -> Self {
         Self(RowIDMap::default())
     }
 }
 
-impl<L, R> IntoIterator for ManyToZeroJoin<L, R> {
+impl<L, R, S> IntoIterator for ManyToZeroJoin<L, R, S>
+where
+    S: RowStorage<(L, Option<R>)>,
+{
     type Item = (L, Option<R>);
-    type IntoIter = IntoValues<i64, Self::Item>;
+    type IntoIter = S::IntoIter;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_iter()
     }
 }
 
-impl<L, R> Deref for ManyToZeroJoin<L, R> {
-    type Target = RowIDMap<L, Option<R>>;
+impl<L, R, S> Deref for ManyToZeroJoin<L, R, S> {
+    type Target = RowIDMap<L, Option<R>, S>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl<L, R> DerefMut for ManyToZeroJoin<L, R> {
+impl<L, R, S> DerefMut for ManyToZeroJoin<L, R, S> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
+
+/// Serialized like its inner [`RowIDMap`]
+#[cfg(feature = "serde")]
+impl<L, R, S> serde::Serialize for ManyToZeroJoin<L, R, S>
+where
+    L: serde::Serialize,
+    R: serde::Serialize,
+    S: RowStorage<(L, Option<R>)>,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, L, R, S> serde::Deserialize<'de> for ManyToZeroJoin<L, R, S>
+where
+    L: HasKey + serde::Deserialize<'de>,
+    R: serde::Deserialize<'de>,
+    S: RowStorage<(L, Option<R>), Key = L::Key>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        RowIDMap::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::KeyedTable;
+    use crate::ManyToZeroJoin;
+    use crate::Table;
+
+    #[test]
+    fn map_test() {
+        let mut join: ManyToZeroJoin<i64, i64> = ManyToZeroJoin::default();
+        join.insert(1, Some(10));
+        join.insert(2, None);
+
+        // For rowids, the keyed types are the default types
+        let join: ManyToZeroJoin<i64, i64> = join.map_left(|left| left + 1);
+        let join: ManyToZeroJoin<i64, String> = join.map_right(|right| right.to_string());
+        assert_eq!(join.get_by_id(2).unwrap().as_deref(), Some("10"));
+
+        let _: Table<i64> = KeyedTable::<i64>::default();
+    }
+}
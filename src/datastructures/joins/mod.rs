@@ -6,6 +6,9 @@ pub mod zero_to_many_join;
 
 pub use join_collection::JoinCollection;
 pub use join_relation::JoinRelation;
+pub use many_to_many_join::KeyedManyToManyJoin;
 pub use many_to_many_join::ManyToManyJoin;
+pub use many_to_zero_join::KeyedManyToZeroJoin;
 pub use many_to_zero_join::ManyToZeroJoin;
+pub use zero_to_many_join::KeyedZeroToManyJoin;
 pub use zero_to_many_join::ZeroToManyJoin;
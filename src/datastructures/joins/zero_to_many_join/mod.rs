@@ -1,67 +1,95 @@
+use core::iter::Chain;
+use core::iter::Map;
+use core::ops::Deref;
+use core::ops::DerefMut;
 use std::collections::HashMap;
-use std::collections::hash_map::IntoValues;
 
 use crate::RowIDMap;
-use crate::has_rowid::HasRowID;
+use crate::datastructures::as_hashmap::AsHashMap;
+use crate::datastructures::as_hashmap::AsHashMapMut;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::HasKey;
+use crate::has_key::IntoKey;
 
 pub mod row;
 /// An [`crate::RowIDMap`] that represent a `LEFT JOIN`, where an element of the Left table <u>can</u> have <u>one</u> element of the Right table
 ///
 /// Example: a **Recording can have <u>many</u> Listens**, but a Listen <u>can</u> have a Recording
+///
+/// Like [`RowIDMap`], the entries can be kept in any [`RowStorage`], indexed by the rowid or the [`HasKey::Key`] of their left element.
+///
+/// The right elements without a left element are kept apart, in the `None` entry. See [`ZeroToManyJoin::without_left`].
+/// Dereferencing the join only gives the entries with a left element
 #[derive(Debug)]
-pub struct ZeroToManyJoin<L, R>(pub(super) RowIDMap<Option<L>, Vec<R>>);
+pub struct ZeroToManyJoin<L, R, S = HashMap<i64, (L, Vec<R>), BuildRowIdHasher>> {
+    entries: RowIDMap<L, Vec<R>, S>,
+    without_left: Option<Vec<R>>,
+}
 
-impl<L, R> ZeroToManyJoin<L, R>
+/// A [`ZeroToManyJoin`] with left elements indexed by a key other than a rowid. For rowids, this is the same type as [`ZeroToManyJoin`]
+pub type KeyedZeroToManyJoin<L, R, H = BuildRowIdHasher> =
+    ZeroToManyJoin<L, R, HashMap<<L as HasKey>::Key, (L, Vec<R>), H>>;
+
+impl<L, R, S> ZeroToManyJoin<L, R, S>
 where
-    L: HasRowID,
+    L: HasKey,
+    S: RowStorage<(L, Vec<R>), Key = L::Key>,
 {
     pub fn insert(&mut self, key: Option<L>, value: Vec<R>) {
-        self.0.insert(key, value);
+        match key {
+            Some(left) => {
+                self.entries.insert(left, value);
+            }
+            None => self.without_left = Some(value),
+        }
     }
 
     /// Push a value to its correponding entry
     pub fn push_entry(&mut self, key: Option<L>, value: R) {
-        self.0
-            .as_mut_hash_map()
-            .entry(key.rowid())
-            .or_insert((key, Vec::new()))
-            .1
-            .push(value);
+        self.entry_values(key).push(value);
     }
 
     /// Push multiple values to its correponding entry
     pub fn push_entries(&mut self, key: Option<L>, value: Vec<R>) {
-        self.0
-            .as_mut_hash_map()
-            .entry(key.rowid())
-            .or_insert((key, Vec::new()))
-            .1
-            .extend(value);
+        self.entry_values(key).extend(value);
+    }
+
+    fn entry_values(&mut self, key: Option<L>) -> &mut Vec<R> {
+        match key {
+            Some(left) => self.entries.entry(left).or_default(),
+            None => self.without_left.get_or_insert_default(),
+        }
     }
 
     pub fn insert_left(&mut self, left: Option<L>) {
-        self.0.insert(left, Vec::new());
+        self.insert(left, Vec::new());
     }
 
     /// Add a right value using an id. If the left value doesn't exists, it won't be inserted
-    pub fn push_right_by_id(&mut self, key: i64, value: R) {
-        self.as_mut_hash_map()
-            .entry(key)
-            .and_modify(|vals| vals.1.push(value));
+    pub fn push_right_by_id<I>(&mut self, key: I, value: R)
+    where
+        I: IntoKey<L>,
+    {
+        if let Some(vals) = self.entries.get_mut_by_id(key) {
+            vals.push(value);
+        }
     }
 
-    pub fn as_hash_map(&self) -> &HashMap<i64, (Option<L>, Vec<R>)> {
-        self.0.as_hash_map()
+    /// The right elements of the `None` entry, which don't have a left element
+    pub fn without_left(&self) -> Option<&Vec<R>> {
+        self.without_left.as_ref()
     }
 
-    pub fn as_mut_hash_map(&mut self) -> &mut HashMap<i64, (Option<L>, Vec<R>)> {
-        self.0.as_mut_hash_map()
+    /// The right elements of the `None` entry, which don't have a left element
+    pub fn without_left_mut(&mut self) -> Option<&mut Vec<R>> {
+        self.without_left.as_mut()
     }
 
-    pub fn map_left<F, U>(self, f: F) -> ZeroToManyJoin<U, R>
+    pub fn map_left<F, U>(self, f: F) -> KeyedZeroToManyJoin<U, R>
     where
         F: Fn(L) -> U,
-        U: HasRowID,
+        U: HasKey,
     {
         let mut new_map = ZeroToManyJoin::default();
 
@@ -73,7 +101,7 @@ where
         new_map
     }
 
-    pub fn map_right<F, U>(self, f: F) -> ZeroToManyJoin<L, U>
+    pub fn map_right<F, U>(self, f: F) -> KeyedZeroToManyJoin<L, U>
     where
         F: FnMut(R) -> U + Clone,
     {
@@ -88,17 +116,203 @@ where
     }
 }
 
-impl<L, R> Default for ZeroToManyJoin<L, R> {
+impl<L, R, H> ZeroToManyJoin<L, R, HashMap<L::Key, (L, Vec<R>), H>>
+where
+    L: HasKey,
+{
+    #[deprecated(note = "use `AsHashMap::as_hashmap` instead")]
+    pub fn as_hash_map(&self) -> &HashMap<L::Key, (L, Vec<R>), H> {
+        self.as_hashmap()
+    }
+
+    #[deprecated(note = "use `AsHashMapMut::as_hashmap_mut` instead")]
+    pub fn as_mut_hash_map(&mut self) -> &mut HashMap<L::Key, (L, Vec<R>), H> {
+        self.as_hashmap_mut()
+    }
+}
+
+/// The entries with a left element
+impl<L, R, H> AsHashMap<L::Key, (L, Vec<R>), H>
+    for ZeroToManyJoin<L, R, HashMap<L::Key, (L, Vec<R>), H>>
+where
+    L: HasKey,
+{
+    fn as_hashmap(&self) -> &HashMap<L::Key, (L, Vec<R>), H> {
+        self.entries.as_hashmap()
+    }
+}
+
+impl<L, R, H> AsHashMapMut<L::Key, (L, Vec<R>), H>
+    for ZeroToManyJoin<L, R, HashMap<L::Key, (L, Vec<R>), H>>
+where
+    L: HasKey,
+{
+    fn as_hashmap_mut(&mut self) -> &mut HashMap<L::Key, (L, Vec<R>), H> {
+        self.entries.as_hashmap_mut()
+    }
+}
+
+impl<L, R, S> Default for ZeroToManyJoin<L, R, S>
+where
+    S: Default,
+{
     fn default() -> Self {
-        Self(RowIDMap::default())
+        Self {
+            entries: RowIDMap::default(),
+            without_left: None,
+        }
     }
 }
 
-impl<L, R> IntoIterator for ZeroToManyJoin<L, R> {
-    type Item = (Option<L>, Vec<R>);
-    type IntoIter = IntoValues<i64, Self::Item>;
+/// An entry of the join, which may not have a left element
+type JoinEntry<L, R> = (Option<L>, Vec<R>);
+
+fn with_left<L, R>((left, right): (L, Vec<R>)) -> JoinEntry<L, R> {
+    (Some(left), right)
+}
+
+/// The entries with a left element, then the `None` entry
+impl<L, R, S> IntoIterator for ZeroToManyJoin<L, R, S>
+where
+    S: RowStorage<(L, Vec<R>)>,
+{
+    type Item = JoinEntry<L, R>;
+    type IntoIter = Chain<
+        Map<S::IntoIter, fn((L, Vec<R>)) -> JoinEntry<L, R>>,
+        core::option::IntoIter<JoinEntry<L, R>>,
+    >;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.entries
+            .into_iter()
+            .map(with_left as fn(_) -> _)
+            .chain(self.without_left.map(|right| (None, right)))
+    }
+}
+
+impl<L, R, S> Deref for ZeroToManyJoin<L, R, S> {
+    type Target = RowIDMap<L, Vec<R>, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl<L, R, S> DerefMut for ZeroToManyJoin<L, R, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entries
+    }
+}
+
+/// Serialized as a sequence of `(Option<left>, rights)` pairs
+#[cfg(feature = "serde")]
+impl<L, R, S> serde::Serialize for ZeroToManyJoin<L, R, S>
+where
+    L: HasKey + serde::Serialize,
+    R: serde::Serialize,
+    S: RowStorage<(L, Vec<R>), Key = L::Key>,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: serde::Serializer,
+    {
+        let entries = self.entries.as_storage().iter();
+        let without_left = self.without_left.iter().map(|right| (None, right));
+
+        serializer.collect_seq(
+            entries
+                .map(|(left, right)| (Some(left), right))
+                .chain(without_left),
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, L, R, S> serde::Deserialize<'de> for ZeroToManyJoin<L, R, S>
+where
+    L: HasKey + serde::Deserialize<'de>,
+    R: serde::Deserialize<'de>,
+    S: RowStorage<(L, Vec<R>), Key = L::Key>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ZeroToManyJoinVisitor<L, R, S>(core::marker::PhantomData<(L, R, S)>);
+
+        impl<'de, L, R, S> serde::de::Visitor<'de> for ZeroToManyJoinVisitor<L, R, S>
+        where
+            L: HasKey + serde::Deserialize<'de>,
+            R: serde::Deserialize<'de>,
+            S: RowStorage<(L, Vec<R>), Key = L::Key>,
+        {
+            type Value = ZeroToManyJoin<L, R, S>;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a sequence of join entries")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut join = ZeroToManyJoin::default();
+
+                while let Some((left, right)) = seq.next_element::<(Option<L>, Vec<R>)>()? {
+                    if left.as_ref().is_some_and(|left| !left.has_valid_key()) {
+                        return Err(serde::de::Error::custom(
+                            "a left element doesn't have a valid key",
+                        ));
+                    }
+
+                    join.insert(left, right);
+                }
+
+                Ok(join)
+            }
+        }
+
+        deserializer.deserialize_seq(ZeroToManyJoinVisitor(core::marker::PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::KeyedZeroToManyJoin;
+    use crate::has_key::ByKey;
+    use crate::has_key::HasKey;
+
+    struct Artist {
+        name: &'static str,
+    }
+
+    impl HasKey for Artist {
+        type Key = &'static str;
+
+        fn key(&self) -> Self::Key {
+            self.name
+        }
+    }
+
+    #[test]
+    fn keyed_join_test() {
+        let mut join: KeyedZeroToManyJoin<Artist, u32> = KeyedZeroToManyJoin::default();
+        join.insert_left(Some(Artist { name: "Mitski" }));
+        join.push_entry(None, 3);
+        join.push_right_by_id(ByKey("Mitski"), 1);
+        join.push_entries(Some(Artist { name: "Mitski" }), vec![2]);
+
+        assert_eq!(join.get_by_id(ByKey("Mitski")), Some(&vec![1, 2]));
+        assert_eq!(join.len(), 1);
+        assert_eq!(join.without_left(), Some(&vec![3]));
+
+        let entries = join
+            .map_right(|right| right * 10)
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].0.is_some());
+        assert!(entries[1].0.is_none());
+        assert_eq!(entries[1].1, [30]);
     }
 }
@@ -0,0 +1,119 @@
+use std::collections::BTreeSet;
+use std::collections::btree_set;
+
+use crate::RowIdSet;
+use crate::datastructures::rowid_set;
+
+/// A set of row keys. This is usually a [`RowIdSet`] for rowids, and a [`BTreeSet`] for the other keys.
+///
+/// It is chosen with the type parameters of [`ManyToManyJoin`](crate::ManyToManyJoin), or the return type of [`Table::key_set`](crate::Table::key_set)
+pub trait KeySet<K>: Default + FromIterator<K> + Extend<K> {
+    type Iter<'a>: Iterator<Item = K>
+    where
+        Self: 'a;
+
+    /// Add a key to the set, returning true if it wasn't in it
+    fn insert(&mut self, key: K) -> bool;
+
+    /// Remove a key from the set, returning true if it was in it
+    fn remove(&mut self, key: &K) -> bool;
+
+    fn contains(&self, key: &K) -> bool;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the keys, in order
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// The keys in either set
+    fn union(&self, other: &Self) -> Self;
+
+    /// The keys in both sets
+    fn intersection(&self, other: &Self) -> Self;
+
+    /// The keys in this set, but not in `other`
+    fn difference(&self, other: &Self) -> Self;
+}
+
+impl KeySet<i64> for RowIdSet {
+    type Iter<'a> = rowid_set::Iter<'a>;
+
+    fn insert(&mut self, key: i64) -> bool {
+        RowIdSet::insert(self, key)
+    }
+
+    fn remove(&mut self, key: &i64) -> bool {
+        RowIdSet::remove(self, *key)
+    }
+
+    fn contains(&self, key: &i64) -> bool {
+        RowIdSet::contains(self, *key)
+    }
+
+    fn len(&self) -> usize {
+        // A set can't hold more rowids than the address space
+        RowIdSet::len(self) as usize
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        RowIdSet::iter(self)
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        RowIdSet::union(self, other)
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        RowIdSet::intersection(self, other)
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        RowIdSet::difference(self, other)
+    }
+}
+
+impl<K> KeySet<K> for BTreeSet<K>
+where
+    K: Ord + Clone,
+{
+    type Iter<'a>
+        = core::iter::Cloned<btree_set::Iter<'a, K>>
+    where
+        Self: 'a;
+
+    fn insert(&mut self, key: K) -> bool {
+        BTreeSet::insert(self, key)
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        BTreeSet::remove(self, key)
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        BTreeSet::contains(self, key)
+    }
+
+    fn len(&self) -> usize {
+        BTreeSet::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        BTreeSet::iter(self).cloned()
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        self | other
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        self & other
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        self - other
+    }
+}
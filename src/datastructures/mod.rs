@@ -1,4 +1,8 @@
 pub mod as_hashmap;
+pub mod hasher;
 pub mod joins;
+pub mod key_set;
 pub mod ranking;
 pub mod rowid_map;
+pub mod rowid_set;
+pub mod storage;
@@ -1,4 +1,9 @@
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct Ranking<T>(pub Vec<T>);
 
 impl<T> Ranking<T> {
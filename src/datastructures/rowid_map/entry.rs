@@ -0,0 +1,194 @@
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::HasKey;
+use crate::has_key::assert_valid_key;
+
+/// A view into a single entry of a [`RowIDMap`](crate::RowIDMap). Created by [`RowIDMap::entry`](crate::RowIDMap::entry)
+pub enum Entry<'m, K, V, S>
+where
+    K: HasKey,
+{
+    Occupied(OccupiedEntry<'m, K, V, S>),
+    Vacant(VacantEntry<'m, K, V, S>),
+}
+
+impl<'m, K, V, S> Entry<'m, K, V, S>
+where
+    K: HasKey + 'm,
+    V: 'm,
+    S: RowStorage<(K, V), Key = K::Key>,
+{
+    /// The rowid, or key, of the entry
+    pub fn id(&self) -> K::Key {
+        match self {
+            Entry::Occupied(entry) => entry.id(),
+            Entry::Vacant(entry) => entry.id(),
+        }
+    }
+
+    /// Insert `default` if the entry is vacant, and return the value
+    pub fn or_insert(self, default: V) -> &'m mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Insert the value returned by `default` if the entry is vacant, and return the value
+    pub fn or_insert_with<F>(self, default: F) -> &'m mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Insert the default value if the entry is vacant, and return the value
+    pub fn or_default(self) -> &'m mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Modify the value if the entry is occupied
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+
+        self
+    }
+}
+
+/// An occupied entry of a [`RowIDMap`](crate::RowIDMap)
+pub struct OccupiedEntry<'m, K, V, S>
+where
+    K: HasKey,
+{
+    storage: &'m mut S,
+    id: K::Key,
+    // The storage can't be borrowed while its value is, so the pair is fetched again on each access
+    _pair: core::marker::PhantomData<(K, V)>,
+}
+
+impl<'m, K, V, S> OccupiedEntry<'m, K, V, S>
+where
+    K: HasKey + 'm,
+    V: 'm,
+    S: RowStorage<(K, V), Key = K::Key>,
+{
+    pub(super) fn new(storage: &'m mut S, id: K::Key) -> Self {
+        Self {
+            storage,
+            id,
+            _pair: core::marker::PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> K::Key {
+        self.id.clone()
+    }
+
+    fn pair(&self) -> &(K, V) {
+        self.storage
+            .get(&self.id)
+            .expect("The entry should be occupied")
+    }
+
+    fn pair_mut(&mut self) -> &mut (K, V) {
+        self.storage
+            .get_mut(&self.id)
+            .expect("The entry should be occupied")
+    }
+
+    /// The key in the map
+    pub fn key(&self) -> &K {
+        &self.pair().0
+    }
+
+    pub fn get(&self) -> &V {
+        &self.pair().1
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.pair_mut().1
+    }
+
+    /// Convert the entry into a mutable reference to its value, bound to the map
+    pub fn into_mut(self) -> &'m mut V {
+        &mut self
+            .storage
+            .get_mut(&self.id)
+            .expect("The entry should be occupied")
+            .1
+    }
+
+    /// Replace the value, returning the old one
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+
+    /// Remove the pair from the map
+    pub fn remove_entry(self) -> (K, V) {
+        self.storage
+            .remove(&self.id)
+            .expect("The entry should be occupied")
+    }
+
+    /// Remove the pair from the map, returning the value
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+/// A vacant entry of a [`RowIDMap`](crate::RowIDMap)
+pub struct VacantEntry<'m, K, V, S> {
+    storage: &'m mut S,
+    key: K,
+    _value: core::marker::PhantomData<V>,
+}
+
+impl<'m, K, V, S> VacantEntry<'m, K, V, S>
+where
+    K: HasKey + 'm,
+    V: 'm,
+    S: RowStorage<(K, V), Key = K::Key>,
+{
+    pub(super) fn new(storage: &'m mut S, key: K) -> Self {
+        Self {
+            storage,
+            key,
+            _value: core::marker::PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> K::Key {
+        self.key.key()
+    }
+
+    /// The key that would be inserted
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Take back the key
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Insert the value, and return a mutable reference to it
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key doesn't have a valid key, like an unsaved row with the rowid 0
+    #[track_caller]
+    pub fn insert(self, value: V) -> &'m mut V {
+        assert_valid_key(&self.key);
+        let id = self.key.key();
+        let key = self.key;
+
+        &mut self.storage.get_or_insert_with(id, || (key, value)).1
+    }
+}
@@ -1,64 +1,380 @@
+use core::marker::PhantomData;
+use core::ops::Index;
+use core::ops::RangeBounds;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::collections::hash_map::IntoValues;
 
-use crate::tables::traits::has_rowid::HasRowID;
+use crate::datastructures::as_hashmap::AsHashMap;
+use crate::datastructures::as_hashmap::AsHashMapMut;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::rowid_map::entry::Entry;
+use crate::datastructures::rowid_map::entry::OccupiedEntry;
+use crate::datastructures::rowid_map::entry::VacantEntry;
+use crate::datastructures::storage::OrderedRowStorage;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::HasKey;
+use crate::has_key::IntoKey;
+use crate::has_key::assert_valid_key;
 
-/// An hashmap that use the rowid of its "key" element as actual key, relieving it from the Eq + Hash requirement
+pub mod entry;
+
+/// An hashmap that use the rowid, or the [`HasKey::Key`], of its "key" element as actual key, relieving it from the Eq + Hash requirement
+///
+/// The pairs are kept in a [`RowStorage`], which is an [`HashMap`] by default.
 #[derive(Debug)]
-pub struct RowIDMap<K, V>(HashMap<i64, (K, V)>);
+pub struct RowIDMap<K, V, S = HashMap<i64, (K, V), BuildRowIdHasher>>(S, PhantomData<(K, V)>);
+
+/// A [`RowIDMap`] that iterates over its pairs in rowid order
+pub type OrderedRowIDMap<K, V> = RowIDMap<K, V, BTreeMap<i64, (K, V)>>;
 
-impl<K, V> RowIDMap<K, V>
+impl<K, V, S> RowIDMap<K, V, S>
 where
-    K: HasRowID,
+    K: HasKey,
+    S: RowStorage<(K, V), Key = K::Key>,
 {
-    pub fn insert(&mut self, key: K, value: V) {
-        self.0.insert(key.rowid(), (key, value));
+    /// Insert a key-value pair, returning the pair previously at this rowid
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key doesn't have a valid key, like an unsaved row with the rowid 0
+    #[track_caller]
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        assert_valid_key(&key);
+        self.0.insert(key.key(), (key, value))
     }
 
-    pub fn get_by_id(&self, key: i64) -> Option<&V> {
-        self.0.get(&key).map(|(_, val)| val)
+    /// Get the entry of the key, for in-place manipulation
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        let id = key.key();
+
+        if self.0.contains(&id) {
+            Entry::Occupied(OccupiedEntry::new(&mut self.0, id))
+        } else {
+            Entry::Vacant(VacantEntry::new(&mut self.0, key))
+        }
+    }
+
+    pub fn get_by_id<I>(&self, key: I) -> Option<&V>
+    where
+        I: IntoKey<K>,
+    {
+        self.0.get(&key.into_key()).map(|(_, val)| val)
     }
 
     pub fn get(&self, key: &K) -> Option<&V> {
-        self.get_by_id(key.rowid())
+        self.0.get(&key.key()).map(|(_, val)| val)
     }
 
-    pub fn get_mut_by_id(&mut self, key: i64) -> Option<&mut V> {
-        self.0.get_mut(&key).map(|(_, val)| val)
+    pub fn get_mut_by_id<I>(&mut self, key: I) -> Option<&mut V>
+    where
+        I: IntoKey<K>,
+    {
+        self.0.get_mut(&key.into_key()).map(|(_, val)| val)
     }
 
     pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
-        self.get_mut_by_id(key.rowid())
+        self.0.get_mut(&key.key()).map(|(_, val)| val)
+    }
+
+    /// Get the key-value pair of a rowid
+    pub fn get_key_value_by_id<I>(&self, key: I) -> Option<(&K, &V)>
+    where
+        I: IntoKey<K>,
+    {
+        self.0.get(&key.into_key()).map(|(key, val)| (key, val))
+    }
+
+    /// Remove a key, returning its value
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.0.remove(&key.key()).map(|(_, val)| val)
+    }
+
+    /// Remove a rowid, returning its value
+    pub fn remove_by_id<I>(&mut self, key: I) -> Option<V>
+    where
+        I: IntoKey<K>,
+    {
+        self.remove_entry_by_id(key).map(|(_, val)| val)
+    }
+
+    /// Remove a rowid, returning its key-value pair
+    pub fn remove_entry_by_id<I>(&mut self, key: I) -> Option<(K, V)>
+    where
+        I: IntoKey<K>,
+    {
+        self.0.remove(&key.into_key())
+    }
+
+    /// Only keep the pairs for which `f` returns true
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.0.retain(|(key, val)| f(key, val));
+    }
+
+    /// Remove all the pairs, returning them as an iterator
+    pub fn drain(&mut self) -> S::IntoIter {
+        core::mem::take(&mut self.0).into_values()
+    }
+
+    /// Remove all the pairs
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.0.iter().map(|(key, val)| (key, val))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.0.iter_mut().map(|(key, val)| (&*key, val))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.0.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.0.iter().map(|(_, val)| val)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.0.iter_mut().map(|(_, val)| val)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 
     pub fn contains_key(&self, key: &K) -> bool {
-        self.0.contains_key(&key.rowid())
+        self.0.contains(&key.key())
     }
 
-    pub fn contains_id(&self, key: i64) -> bool {
-        self.0.contains_key(&key)
+    pub fn contains_id<I>(&self, key: I) -> bool
+    where
+        I: IntoKey<K>,
+    {
+        self.0.contains(&key.into_key())
     }
 
-    pub fn as_hash_map(&self) -> &HashMap<i64, (K, V)> {
+    /// Return the underlying storage
+    pub fn as_storage(&self) -> &S {
         &self.0
     }
 
-    pub fn as_mut_hash_map(&mut self) -> &mut HashMap<i64, (K, V)> {
+    /// Return the underlying storage
+    pub fn as_mut_storage(&mut self) -> &mut S {
         &mut self.0
     }
 }
 
-impl<K, V> Default for RowIDMap<K, V> {
+impl<K, V, S> RowIDMap<K, V, S>
+where
+    K: HasKey,
+    S: OrderedRowStorage<(K, V), Key = K::Key>,
+{
+    /// Iterate over the pairs with a key rowid in the given range, in rowid order
+    pub fn range<B>(&self, range: B) -> impl DoubleEndedIterator<Item = (&K, &V)>
+    where
+        B: RangeBounds<K::Key>,
+    {
+        self.0.range(range).map(|(key, val)| (key, val))
+    }
+
+    /// The pair with the smallest rowid
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.0.first().map(|(key, val)| (key, val))
+    }
+
+    /// The pair with the greatest rowid
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.0.last().map(|(key, val)| (key, val))
+    }
+}
+
+impl<K, V, H> RowIDMap<K, V, HashMap<K::Key, (K, V), H>>
+where
+    K: HasKey,
+{
+    #[deprecated(note = "use `AsHashMap::as_hashmap` instead")]
+    pub fn as_hash_map(&self) -> &HashMap<K::Key, (K, V), H> {
+        self.as_hashmap()
+    }
+
+    #[deprecated(note = "use `AsHashMapMut::as_hashmap_mut` instead")]
+    pub fn as_mut_hash_map(&mut self) -> &mut HashMap<K::Key, (K, V), H> {
+        self.as_hashmap_mut()
+    }
+}
+
+impl<K, V, H> AsHashMap<K::Key, (K, V), H> for RowIDMap<K, V, HashMap<K::Key, (K, V), H>>
+where
+    K: HasKey,
+{
+    fn as_hashmap(&self) -> &HashMap<K::Key, (K, V), H> {
+        &self.0
+    }
+}
+
+impl<K, V, H> AsHashMapMut<K::Key, (K, V), H> for RowIDMap<K, V, HashMap<K::Key, (K, V), H>>
+where
+    K: HasKey,
+{
+    fn as_hashmap_mut(&mut self) -> &mut HashMap<K::Key, (K, V), H> {
+        &mut self.0
+    }
+}
+
+impl<K, V, S> Default for RowIDMap<K, V, S>
+where
+    S: Default,
+{
     fn default() -> Self {
-        Self(HashMap::default())
+        Self(S::default(), PhantomData)
+    }
+}
+
+/// Get the value of a rowid, or of a key.
+///
+/// # Panics
+///
+/// Panics if the rowid isn't in the map
+impl<K, V, S, I> Index<I> for RowIDMap<K, V, S>
+where
+    K: HasKey,
+    S: RowStorage<(K, V), Key = K::Key>,
+    I: IntoKey<K>,
+{
+    type Output = V;
+
+    fn index(&self, index: I) -> &Self::Output {
+        self.get_by_id(index)
+            .expect("The rowid should be in the map")
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for RowIDMap<K, V, S>
+where
+    K: HasKey,
+    S: RowStorage<(K, V), Key = K::Key>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for RowIDMap<K, V, S>
+where
+    K: HasKey,
+    S: RowStorage<(K, V), Key = K::Key>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
     }
 }
 
-impl<K, V> IntoIterator for RowIDMap<K, V> {
+impl<K, V, S> IntoIterator for RowIDMap<K, V, S>
+where
+    S: RowStorage<(K, V)>,
+{
     type Item = (K, V);
-    type IntoIter = IntoValues<i64, (K, V)>;
+    type IntoIter = S::IntoIter;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_values()
     }
 }
+
+/// Serialized as a sequence of `(key, value)` pairs
+#[cfg(feature = "serde")]
+impl<K, V, S> serde::Serialize for RowIDMap<K, V, S>
+where
+    K: serde::Serialize,
+    V: serde::Serialize,
+    S: RowStorage<(K, V)>,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, S> serde::Deserialize<'de> for RowIDMap<K, V, S>
+where
+    K: HasKey + serde::Deserialize<'de>,
+    V: serde::Deserialize<'de>,
+    S: RowStorage<(K, V), Key = K::Key>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct RowIDMapVisitor<K, V, S>(PhantomData<(K, V, S)>);
+
+        impl<'de, K, V, S> serde::de::Visitor<'de> for RowIDMapVisitor<K, V, S>
+        where
+            K: HasKey + serde::Deserialize<'de>,
+            V: serde::Deserialize<'de>,
+            S: RowStorage<(K, V), Key = K::Key>,
+        {
+            type Value = RowIDMap<K, V, S>;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a sequence of key-value pairs")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut map = RowIDMap::default();
+
+                while let Some((key, value)) = seq.next_element::<(K, V)>()? {
+                    if !key.has_valid_key() {
+                        return Err(serde::de::Error::custom("a key doesn't have a valid key"));
+                    }
+
+                    map.insert(key, value);
+                }
+
+                Ok(map)
+            }
+        }
+
+        deserializer.deserialize_seq(RowIDMapVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RowIDMap;
+
+    #[test]
+    fn rowid_map_test() {
+        let mut map: RowIDMap<i64, Vec<&str>> = [(1, vec!["a"]), (2, vec![])].into_iter().collect();
+
+        map.entry(1).or_default().push("b");
+        map.entry(3).or_insert_with(|| vec!["c"]);
+        assert_eq!(map[1], ["a", "b"]);
+        assert_eq!(map.len(), 3);
+
+        map.retain(|_, val| !val.is_empty());
+        assert!(!map.contains_id(2));
+
+        assert_eq!(map.remove(&3), Some(vec!["c"]));
+        assert_eq!(map.drain().count(), 1);
+        assert!(map.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+use core::ops::BitAnd;
+use core::ops::BitAndAssign;
+use core::ops::BitOr;
+use core::ops::BitOrAssign;
+use core::ops::BitXor;
+use core::ops::BitXorAssign;
+use core::ops::Sub;
+use core::ops::SubAssign;
+
+use roaring::RoaringTreemap;
+use roaring::treemap::IntoIter as TreemapIntoIter;
+use roaring::treemap::Iter as TreemapIter;
+
+/// A compressed set of rowids, backed by a roaring bitmap.
+///
+/// It takes a fraction of the memory of a `HashSet<i64>` for dense rowids, and the set operations work on whole chunks of rowids at once.
+/// Use the `|`, `&`, `-` and `^` operators, or their named methods, to combine sets.
+///
+/// The rowids are iterated in order.
+///
+/// # Exemple
+/// ```
+/// # use sequelles::RowIdSet;
+/// let listened: RowIdSet = (1..=100_000).collect();
+/// let fetched: RowIdSet = (1..=99_998).collect();
+///
+/// let to_fetch = &listened - &fetched;
+/// assert_eq!(to_fetch.iter().collect::<Vec<_>>(), [99_999, 100_000]);
+/// ```
+#[derive(Clone, Default, PartialEq)]
+pub struct RowIdSet(RoaringTreemap);
+
+/// Map a rowid to the bitmap, keeping the order of the negative rowids
+fn to_bits(rowid: i64) -> u64 {
+    (rowid as u64) ^ (1 << 63)
+}
+
+fn from_bits(bits: u64) -> i64 {
+    (bits ^ (1 << 63)) as i64
+}
+
+impl RowIdSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rowid to the set, returning true if it wasn't in it
+    pub fn insert(&mut self, rowid: i64) -> bool {
+        self.0.insert(to_bits(rowid))
+    }
+
+    /// Remove a rowid from the set, returning true if it was in it
+    pub fn remove(&mut self, rowid: i64) -> bool {
+        self.0.remove(to_bits(rowid))
+    }
+
+    pub fn contains(&self, rowid: i64) -> bool {
+        self.0.contains(to_bits(rowid))
+    }
+
+    pub fn len(&self) -> u64 {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// The smallest rowid
+    pub fn first(&self) -> Option<i64> {
+        self.0.min().map(from_bits)
+    }
+
+    /// The greatest rowid
+    pub fn last(&self) -> Option<i64> {
+        self.0.max().map(from_bits)
+    }
+
+    /// Iterate over the rowids, in order
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.0.iter())
+    }
+
+    /// The rowids in either set
+    pub fn union(&self, other: &Self) -> Self {
+        self | other
+    }
+
+    /// The rowids in both sets
+    pub fn intersection(&self, other: &Self) -> Self {
+        self & other
+    }
+
+    /// The rowids in this set, but not in `other`
+    pub fn difference(&self, other: &Self) -> Self {
+        self - other
+    }
+
+    /// The rowids in only one of the sets
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self ^ other
+    }
+
+    /// The number of rowids in both sets, without building the intersection
+    pub fn intersection_len(&self, other: &Self) -> u64 {
+        self.0.intersection_len(&other.0)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.0.is_disjoint(&other.0)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.0.is_subset(&other.0)
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        self.0.is_superset(&other.0)
+    }
+}
+
+impl Eq for RowIdSet {}
+
+impl core::fmt::Debug for RowIdSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<i64> for RowIdSet {
+    fn from_iter<I: IntoIterator<Item = i64>>(iter: I) -> Self {
+        Self(iter.into_iter().map(to_bits).collect())
+    }
+}
+
+impl Extend<i64> for RowIdSet {
+    fn extend<I: IntoIterator<Item = i64>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(to_bits));
+    }
+}
+
+impl<'a> IntoIterator for &'a RowIdSet {
+    type Item = i64;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for RowIdSet {
+    type Item = i64;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.0.into_iter())
+    }
+}
+
+/// An iterator over the rowids of a [`RowIdSet`], in order
+pub struct Iter<'a>(TreemapIter<'a>);
+
+impl Iterator for Iter<'_> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(from_bits)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(from_bits)
+    }
+}
+
+/// An owning iterator over the rowids of a [`RowIdSet`], in order
+pub struct IntoIter(TreemapIntoIter);
+
+impl Iterator for IntoIter {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(from_bits)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(from_bits)
+    }
+}
+
+/// Implement a set operator for references, and its assigning version
+macro_rules! set_operator {
+    ($op:ident, $method:ident, $assign_op:ident, $assign_method:ident) => {
+        impl $op<&RowIdSet> for &RowIdSet {
+            type Output = RowIdSet;
+
+            fn $method(self, rhs: &RowIdSet) -> Self::Output {
+                RowIdSet($op::$method(&self.0, &rhs.0))
+            }
+        }
+
+        impl $assign_op<&RowIdSet> for RowIdSet {
+            fn $assign_method(&mut self, rhs: &RowIdSet) {
+                $assign_op::$assign_method(&mut self.0, &rhs.0);
+            }
+        }
+    };
+}
+
+set_operator!(BitOr, bitor, BitOrAssign, bitor_assign);
+set_operator!(BitAnd, bitand, BitAndAssign, bitand_assign);
+set_operator!(Sub, sub, SubAssign, sub_assign);
+set_operator!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+/// Serialized as a sequence of rowids
+#[cfg(feature = "serde")]
+impl serde::Serialize for RowIdSet {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: serde::Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RowIdSet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Vec::<i64>::deserialize(deserializer).map(Self::from_iter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::RowIdSet;
+
+    #[test]
+    fn rowid_set_test() {
+        let a: RowIdSet = [-2, 1, 2, 3, 1 << 40].into_iter().collect();
+        let b: RowIdSet = [2, 3, 4].into_iter().collect();
+
+        assert_eq!(a.iter().collect::<Vec<_>>(), [-2, 1, 2, 3, 1 << 40]);
+        assert_eq!((&a & &b).iter().collect::<Vec<_>>(), [2, 3]);
+        assert_eq!((&a | &b).len(), 6);
+        assert_eq!((&a - &b).iter().collect::<Vec<_>>(), [-2, 1, 1 << 40]);
+        assert_eq!((&a ^ &b).iter().collect::<Vec<_>>(), [-2, 1, 4, 1 << 40]);
+        assert_eq!(a.intersection_len(&b), 2);
+        assert_eq!(a.first(), Some(-2));
+        assert!(!a.contains(4));
+    }
+}
@@ -0,0 +1,237 @@
+use core::hash::BuildHasher;
+use std::collections::HashMap;
+
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::storage::RowStorage;
+use crate::datastructures::storage::dense::DenseStorage;
+
+/// Below this many slots, a dense storage is always kept
+const MIN_DENSE_SPAN: u64 = 1024;
+
+/// A dense storage is kept as long as it has less than this many slots per value
+const MAX_SLOTS_PER_VALUE: u64 = 4;
+
+/// A [`RowStorage`] that starts as a [`DenseStorage`], and switches to an [`HashMap`] once the rowids become too sparse.
+///
+/// This is a good choice when the rowids are expected to be contiguous, but it can't be guaranteed.
+///
+/// It must be picked explicitly, like `Table<R, AdaptiveStorage<R>>`. [`Table`](crate::Table), [`RowIDMap`](crate::RowIDMap)
+/// and the joins keep an [`HashMap`] by default, as they give access to it with [`AsHashMap`](crate::datastructures::as_hashmap::AsHashMap).
+#[derive(Debug, Clone)]
+pub enum AdaptiveStorage<V, H = BuildRowIdHasher> {
+    Dense(DenseStorage<V>),
+    Sparse(HashMap<i64, V, H>),
+}
+
+impl<V, H> AdaptiveStorage<V, H>
+where
+    H: BuildHasher + Default,
+{
+    /// Return true if the values are stored densely
+    pub fn is_dense(&self) -> bool {
+        matches!(self, Self::Dense(_))
+    }
+
+    fn make_sparse(&mut self) {
+        let Self::Dense(dense) = core::mem::replace(self, Self::Sparse(HashMap::default())) else {
+            return;
+        };
+
+        let mut map = HashMap::with_capacity_and_hasher(dense.len(), H::default());
+        map.extend(dense.into_entries());
+        *self = Self::Sparse(map);
+    }
+}
+
+impl<V, H> Default for AdaptiveStorage<V, H> {
+    fn default() -> Self {
+        Self::Dense(DenseStorage::default())
+    }
+}
+
+impl<V, H> RowStorage<V> for AdaptiveStorage<V, H>
+where
+    H: BuildHasher + Default,
+{
+    type Key = i64;
+
+    type Iter<'a>
+        = Either<
+        <DenseStorage<V> as RowStorage<V>>::Iter<'a>,
+        <HashMap<i64, V, H> as RowStorage<V>>::Iter<'a>,
+    >
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IterMut<'a>
+        = Either<
+        <DenseStorage<V> as RowStorage<V>>::IterMut<'a>,
+        <HashMap<i64, V, H> as RowStorage<V>>::IterMut<'a>,
+    >
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IntoIter = Either<
+        <DenseStorage<V> as RowStorage<V>>::IntoIter,
+        <HashMap<i64, V, H> as RowStorage<V>>::IntoIter,
+    >;
+
+    fn insert(&mut self, rowid: i64, value: V) -> Option<V> {
+        if let Self::Dense(dense) = self {
+            let span = dense.span_with(rowid);
+
+            let too_sparse =
+                span > MIN_DENSE_SPAN && span > (dense.len() as u64 + 1) * MAX_SLOTS_PER_VALUE;
+            if too_sparse || !dense.can_insert(rowid) {
+                self.make_sparse();
+            }
+        }
+
+        match self {
+            Self::Dense(dense) => dense.insert(rowid, value),
+            Self::Sparse(map) => RowStorage::insert(map, rowid, value),
+        }
+    }
+
+    fn get(&self, rowid: &i64) -> Option<&V> {
+        match self {
+            Self::Dense(dense) => dense.get(rowid),
+            Self::Sparse(map) => RowStorage::get(map, rowid),
+        }
+    }
+
+    fn get_mut(&mut self, rowid: &i64) -> Option<&mut V> {
+        match self {
+            Self::Dense(dense) => dense.get_mut(rowid),
+            Self::Sparse(map) => RowStorage::get_mut(map, rowid),
+        }
+    }
+
+    fn get_or_insert_with<F>(&mut self, rowid: i64, f: F) -> &mut V
+    where
+        F: FnOnce() -> V,
+    {
+        if !self.contains(&rowid) {
+            self.insert(rowid, f());
+        }
+
+        self.get_mut(&rowid).expect("The value should be inserted")
+    }
+
+    fn remove(&mut self, rowid: &i64) -> Option<V> {
+        match self {
+            Self::Dense(dense) => dense.remove(rowid),
+            Self::Sparse(map) => RowStorage::remove(map, rowid),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Dense(dense) => dense.len(),
+            Self::Sparse(map) => map.len(),
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut V) -> bool,
+    {
+        match self {
+            Self::Dense(dense) => dense.retain(f),
+            Self::Sparse(map) => RowStorage::retain(map, f),
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        match self {
+            Self::Dense(dense) => Either::Left(dense.iter()),
+            Self::Sparse(map) => Either::Right(RowStorage::iter(map)),
+        }
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        match self {
+            Self::Dense(dense) => Either::Left(dense.iter_mut()),
+            Self::Sparse(map) => Either::Right(RowStorage::iter_mut(map)),
+        }
+    }
+
+    fn into_values(self) -> Self::IntoIter {
+        match self {
+            Self::Dense(dense) => Either::Left(dense.into_values()),
+            Self::Sparse(map) => Either::Right(RowStorage::into_values(map)),
+        }
+    }
+}
+
+/// An iterator over either of two storages
+#[derive(Debug, Clone)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A, B> Iterator for Either<A, B>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Left(iter) => iter.next(),
+            Self::Right(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Left(iter) => iter.size_hint(),
+            Self::Right(iter) => iter.size_hint(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datastructures::storage::RowStorage;
+    use crate::datastructures::storage::adaptive::AdaptiveStorage;
+
+    #[test]
+    fn adaptive_storage_test() {
+        let mut storage: AdaptiveStorage<i64> = AdaptiveStorage::default();
+
+        for id in (1..=100).rev() {
+            storage.insert(id, id);
+        }
+        storage.remove(&50);
+
+        assert!(storage.is_dense());
+        assert_eq!(storage.len(), 99);
+        assert_eq!(storage.get(&1), Some(&1));
+        assert_eq!(storage.get(&50), None);
+        assert_eq!(
+            storage.iter().copied().collect::<Vec<_>>(),
+            (1..=100).filter(|id| *id != 50).collect::<Vec<_>>()
+        );
+
+        storage.insert(1_000_000, 1_000_000);
+
+        assert!(!storage.is_dense());
+        assert_eq!(storage.len(), 100);
+        assert_eq!(storage.get(&1_000_000), Some(&1_000_000));
+        assert_eq!(storage.get(&100), Some(&100));
+
+        let mut storage: AdaptiveStorage<i64> = AdaptiveStorage::default();
+        storage.insert(i64::MIN, 1);
+        storage.insert(i64::MAX, 2);
+        assert!(!storage.is_dense());
+    }
+}
@@ -0,0 +1,132 @@
+use core::ops::Bound;
+use core::ops::RangeBounds;
+use std::collections::BTreeMap;
+use std::collections::btree_map::IntoValues;
+use std::collections::btree_map::Range;
+use std::collections::btree_map::Values;
+use std::collections::btree_map::ValuesMut;
+
+use crate::datastructures::storage::OrderedRowStorage;
+use crate::datastructures::storage::RowStorage;
+
+impl<K, V> RowStorage<V> for BTreeMap<K, V>
+where
+    K: Ord,
+{
+    type Key = K;
+
+    type Iter<'a>
+        = Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IterMut<'a>
+        = ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IntoIter = IntoValues<K, V>;
+
+    fn insert(&mut self, key: Self::Key, value: V) -> Option<V> {
+        BTreeMap::insert(self, key, value)
+    }
+
+    fn get(&self, key: &Self::Key) -> Option<&V> {
+        BTreeMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut V> {
+        BTreeMap::get_mut(self, key)
+    }
+
+    fn get_or_insert_with<F>(&mut self, key: Self::Key, f: F) -> &mut V
+    where
+        F: FnOnce() -> V,
+    {
+        self.entry(key).or_insert_with(f)
+    }
+
+    fn remove(&mut self, key: &Self::Key) -> Option<V> {
+        BTreeMap::remove(self, key)
+    }
+
+    fn contains(&self, key: &Self::Key) -> bool {
+        self.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn clear(&mut self) {
+        BTreeMap::clear(self)
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut V) -> bool,
+    {
+        BTreeMap::retain(self, |_, val| f(val))
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.values()
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.values_mut()
+    }
+
+    fn into_values(self) -> Self::IntoIter {
+        BTreeMap::into_values(self)
+    }
+}
+
+impl<K, V> OrderedRowStorage<V> for BTreeMap<K, V>
+where
+    K: Ord,
+{
+    type Range<'a>
+        = core::iter::Map<Range<'a, K, V>, fn((&'a K, &'a V)) -> &'a V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    /// Unlike [`BTreeMap::range`], an inverted range is empty instead of panicking
+    fn range<B>(&self, range: B) -> Self::Range<'_>
+    where
+        B: RangeBounds<K>,
+    {
+        let range = if is_inverted(range.start_bound(), range.end_bound()) {
+            Range::default()
+        } else {
+            BTreeMap::range(self, range)
+        };
+
+        range.map(|(_, val)| val)
+    }
+
+    fn first(&self) -> Option<&V> {
+        self.first_key_value().map(|(_, val)| val)
+    }
+
+    fn last(&self) -> Option<&V> {
+        self.last_key_value().map(|(_, val)| val)
+    }
+}
+
+/// Whether the range has no key, in a way [`BTreeMap::range`] would panic on
+fn is_inverted<K>(start: Bound<&K>, end: Bound<&K>) -> bool
+where
+    K: Ord,
+{
+    match (start, end) {
+        (Bound::Included(start), Bound::Included(end)) => start > end,
+        (Bound::Included(start), Bound::Excluded(end))
+        | (Bound::Excluded(start), Bound::Included(end)) => start > end,
+        (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+        (Bound::Unbounded, _) | (_, Bound::Unbounded) => false,
+    }
+}
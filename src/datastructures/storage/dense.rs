@@ -0,0 +1,301 @@
+use core::iter::Flatten;
+use core::ops::Bound;
+use core::ops::RangeBounds;
+use std::collections::VecDeque;
+
+use crate::datastructures::storage::OrderedRowStorage;
+use crate::datastructures::storage::RowStorage;
+
+/// A [`RowStorage`] backed by a [`VecDeque`] of slots, one per rowid between the smallest and greatest rowid.
+///
+/// This is faster and smaller than an hashmap when the rowids are mostly contiguous, like autoincrement ids.
+/// The slots grow on both ends, so inserting rows in descending rowid order is as fast as in ascending order.
+/// However, a single outlier rowid will make it allocate all the slots in between.
+/// Use [`AdaptiveStorage`](crate::datastructures::storage::adaptive::AdaptiveStorage) if this can happen.
+#[derive(Debug, Clone)]
+pub struct DenseStorage<V> {
+    /// The rowid of the first slot
+    offset: i64,
+    slots: VecDeque<Option<V>>,
+    len: usize,
+}
+
+impl<V> DenseStorage<V> {
+    /// The number of slots needed to hold all the current rowids, and `rowid`
+    pub fn span_with(&self, rowid: i64) -> u64 {
+        if self.slots.is_empty() {
+            return 1;
+        }
+
+        let start = self.offset.min(rowid);
+        let end = self.last_rowid().max(rowid);
+        end.abs_diff(start).saturating_add(1)
+    }
+
+    /// Return true if `rowid` can be inserted without needing more slots than can be allocated
+    pub fn can_insert(&self, rowid: i64) -> bool {
+        let max_span = isize::MAX as u64 / size_of::<Option<V>>().max(1) as u64;
+        self.span_with(rowid) <= max_span
+    }
+
+    /// The rowid of the last slot
+    fn last_rowid(&self) -> i64 {
+        self.offset + (self.slots.len() as i64 - 1)
+    }
+
+    /// The number of allocated slots
+    pub fn span(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Iterate over the rowids and values
+    pub fn iter_entries(&self) -> impl DoubleEndedIterator<Item = (i64, &V)> {
+        let offset = self.offset;
+
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, slot)| slot.as_ref().map(|val| (offset + i as i64, val)))
+    }
+
+    /// Convert the storage into its rowids and values
+    pub fn into_entries(self) -> impl DoubleEndedIterator<Item = (i64, V)> {
+        let offset = self.offset;
+
+        self.slots
+            .into_iter()
+            .enumerate()
+            .filter_map(move |(i, slot)| slot.map(|val| (offset + i as i64, val)))
+    }
+
+    fn index_of(&self, rowid: i64) -> Option<usize> {
+        let index = usize::try_from(rowid.checked_sub(self.offset)?).ok()?;
+        (index < self.slots.len()).then_some(index)
+    }
+
+    /// Convert a rowid bound into a slot index bound, clamped to the slots
+    fn start_index(&self, bound: Bound<&i64>) -> usize {
+        let start = match bound {
+            Bound::Included(id) => *id,
+            Bound::Excluded(id) => id.saturating_add(1),
+            Bound::Unbounded => return 0,
+        };
+
+        usize::try_from(start.saturating_sub(self.offset))
+            .unwrap_or(0)
+            .min(self.slots.len())
+    }
+
+    fn end_index(&self, bound: Bound<&i64>) -> usize {
+        let end = match bound {
+            Bound::Included(id) => id.saturating_add(1),
+            Bound::Excluded(id) => *id,
+            Bound::Unbounded => return self.slots.len(),
+        };
+
+        usize::try_from(end.saturating_sub(self.offset))
+            .unwrap_or(0)
+            .min(self.slots.len())
+    }
+}
+
+impl<V> Default for DenseStorage<V> {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            slots: VecDeque::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<V> RowStorage<V> for DenseStorage<V> {
+    type Key = i64;
+
+    type Iter<'a>
+        = Flatten<std::collections::vec_deque::Iter<'a, Option<V>>>
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IterMut<'a>
+        = Flatten<std::collections::vec_deque::IterMut<'a, Option<V>>>
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IntoIter = Flatten<std::collections::vec_deque::IntoIter<Option<V>>>;
+
+    /// Insert a value, allocating the slots up to its rowid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rowid is too far from the other rowids to allocate the slots in between. See [`DenseStorage::can_insert`]
+    fn insert(&mut self, rowid: i64, value: V) -> Option<V> {
+        assert!(
+            self.can_insert(rowid),
+            "The rowid {rowid} is too far from the other rowids to be stored densely"
+        );
+
+        if self.slots.is_empty() {
+            self.offset = rowid;
+        } else if rowid < self.offset {
+            // The span was checked, so the distances fit in an usize
+            let missing = self.offset.abs_diff(rowid) as usize;
+            self.slots.reserve(missing);
+            for _ in 0..missing {
+                self.slots.push_front(None);
+            }
+            self.offset = rowid;
+        }
+
+        let index = rowid.abs_diff(self.offset) as usize;
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+
+        let old = self.slots[index].replace(value);
+        if old.is_none() {
+            self.len += 1;
+        }
+
+        old
+    }
+
+    fn get(&self, rowid: &i64) -> Option<&V> {
+        self.slots[self.index_of(*rowid)?].as_ref()
+    }
+
+    fn get_mut(&mut self, rowid: &i64) -> Option<&mut V> {
+        let index = self.index_of(*rowid)?;
+        self.slots[index].as_mut()
+    }
+
+    fn get_or_insert_with<F>(&mut self, rowid: i64, f: F) -> &mut V
+    where
+        F: FnOnce() -> V,
+    {
+        if !self.contains(&rowid) {
+            self.insert(rowid, f());
+        }
+
+        self.get_mut(&rowid).expect("The value should be inserted")
+    }
+
+    fn remove(&mut self, rowid: &i64) -> Option<V> {
+        let index = self.index_of(*rowid)?;
+        let old = self.slots[index].take();
+
+        if old.is_some() {
+            self.len -= 1;
+
+            if self.len == 0 {
+                self.clear();
+            }
+        }
+
+        old
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.offset = 0;
+        self.len = 0;
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut V) -> bool,
+    {
+        for slot in &mut self.slots {
+            if slot.as_mut().is_some_and(|val| !f(val)) {
+                *slot = None;
+                self.len -= 1;
+            }
+        }
+
+        if self.len == 0 {
+            self.clear();
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.slots.iter().flatten()
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.slots.iter_mut().flatten()
+    }
+
+    fn into_values(self) -> Self::IntoIter {
+        self.slots.into_iter().flatten()
+    }
+}
+
+impl<V> OrderedRowStorage<V> for DenseStorage<V> {
+    type Range<'a>
+        = Flatten<std::collections::vec_deque::Iter<'a, Option<V>>>
+    where
+        Self: 'a,
+        V: 'a;
+
+    fn range<B>(&self, range: B) -> Self::Range<'_>
+    where
+        B: RangeBounds<i64>,
+    {
+        let start = self.start_index(range.start_bound());
+        let end = self.end_index(range.end_bound()).max(start);
+
+        self.slots.range(start..end).flatten()
+    }
+
+    fn first(&self) -> Option<&V> {
+        self.iter().next()
+    }
+
+    fn last(&self) -> Option<&V> {
+        self.iter().next_back()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datastructures::storage::OrderedRowStorage;
+    use crate::datastructures::storage::RowStorage;
+    use crate::datastructures::storage::dense::DenseStorage;
+
+    #[test]
+    fn dense_storage_test() {
+        let mut storage = DenseStorage::default();
+
+        for id in (-5..=100_000).rev() {
+            storage.insert(id, id);
+        }
+        assert_eq!(storage.span(), 100_006);
+        assert_eq!(
+            storage.range(-1..2).copied().collect::<Vec<_>>(),
+            [-1, 0, 1]
+        );
+        assert_eq!(storage.first(), Some(&-5));
+
+        assert!(!storage.can_insert(i64::MIN));
+        assert!(!storage.can_insert(i64::MAX));
+
+        storage.retain(|_| false);
+        assert_eq!(storage.span(), 0);
+        storage.insert(7, 7);
+        assert_eq!(storage.get(&7), Some(&7));
+    }
+
+    #[test]
+    #[should_panic = "too far from the other rowids"]
+    fn dense_storage_span_test() {
+        let mut storage = DenseStorage::default();
+        storage.insert(1, 1);
+        storage.insert(i64::MAX, 2);
+    }
+}
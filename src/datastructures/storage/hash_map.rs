@@ -0,0 +1,84 @@
+use core::hash::BuildHasher;
+use core::hash::Hash;
+use std::collections::HashMap;
+use std::collections::hash_map::IntoValues;
+use std::collections::hash_map::Values;
+use std::collections::hash_map::ValuesMut;
+
+use crate::datastructures::storage::RowStorage;
+
+impl<K, V, H> RowStorage<V> for HashMap<K, V, H>
+where
+    K: Eq + Hash,
+    H: BuildHasher + Default,
+{
+    type Key = K;
+
+    type Iter<'a>
+        = Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IterMut<'a>
+        = ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IntoIter = IntoValues<K, V>;
+
+    fn insert(&mut self, key: Self::Key, value: V) -> Option<V> {
+        HashMap::insert(self, key, value)
+    }
+
+    fn get(&self, key: &Self::Key) -> Option<&V> {
+        HashMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut V> {
+        HashMap::get_mut(self, key)
+    }
+
+    fn get_or_insert_with<F>(&mut self, key: Self::Key, f: F) -> &mut V
+    where
+        F: FnOnce() -> V,
+    {
+        self.entry(key).or_insert_with(f)
+    }
+
+    fn remove(&mut self, key: &Self::Key) -> Option<V> {
+        HashMap::remove(self, key)
+    }
+
+    fn contains(&self, key: &Self::Key) -> bool {
+        self.contains_key(key)
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn clear(&mut self) {
+        HashMap::clear(self)
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut V) -> bool,
+    {
+        HashMap::retain(self, |_, val| f(val))
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.values()
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.values_mut()
+    }
+
+    fn into_values(self) -> Self::IntoIter {
+        HashMap::into_values(self)
+    }
+}
@@ -0,0 +1,82 @@
+use core::ops::RangeBounds;
+
+pub mod adaptive;
+pub mod btree_map;
+pub mod dense;
+pub mod hash_map;
+
+/// A backend holding values indexed by key, usually a rowid. This is what [`Table`](crate::Table) and [`RowIDMap`](crate::RowIDMap) use to store their data.
+///
+/// The keys aren't given back on iteration, as the stored values are expected to carry them.
+pub trait RowStorage<V>: Default {
+    /// The key of the values. This is the [`HasKey::Key`](crate::has_key::HasKey::Key) of the stored rows
+    type Key;
+
+    type Iter<'a>: Iterator<Item = &'a V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IterMut<'a>: Iterator<Item = &'a mut V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    type IntoIter: Iterator<Item = V>;
+
+    /// Insert a value, returning the one previously at this key
+    fn insert(&mut self, key: Self::Key, value: V) -> Option<V>;
+
+    fn get(&self, key: &Self::Key) -> Option<&V>;
+
+    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut V>;
+
+    /// Get the value at this key, or insert the one returned by `f`
+    fn get_or_insert_with<F>(&mut self, key: Self::Key, f: F) -> &mut V
+    where
+        F: FnOnce() -> V;
+
+    fn remove(&mut self, key: &Self::Key) -> Option<V>;
+
+    fn contains(&self, key: &Self::Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn clear(&mut self);
+
+    /// Only keep the values for which `f` returns true
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut V) -> bool;
+
+    fn iter(&self) -> Self::Iter<'_>;
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
+
+    fn into_values(self) -> Self::IntoIter;
+}
+
+/// A [`RowStorage`] that keeps its values sorted by key. Iterating over it always yields the values in key order.
+pub trait OrderedRowStorage<V>: RowStorage<V> {
+    type Range<'a>: DoubleEndedIterator<Item = &'a V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    /// Iterate over the values with a key in the given range
+    fn range<B>(&self, range: B) -> Self::Range<'_>
+    where
+        B: RangeBounds<Self::Key>;
+
+    /// The value with the smallest key
+    fn first(&self) -> Option<&V>;
+
+    /// The value with the greatest key
+    fn last(&self) -> Option<&V>;
+}
@@ -1,9 +1,26 @@
+// Allow the derive macros to refer to `::sequelles` inside this crate
+#[cfg(feature = "derive")]
+extern crate self as sequelles;
+
 pub mod databases;
 pub mod datastructures;
 pub mod tables;
 
+pub use crate::datastructures::rowid_map::OrderedRowIDMap;
 pub use crate::datastructures::rowid_map::RowIDMap;
+pub use crate::datastructures::rowid_set::RowIdSet;
+pub use crate::tables::indexed_table::IndexedTable;
+pub use crate::tables::lifecycle::NewRow;
+pub use crate::tables::lifecycle::Persisted;
+pub use crate::tables::lifecycle::StagingTable;
+pub use crate::tables::paginate::Paginator;
+pub use crate::tables::shared_table::SharedTable;
+pub use crate::tables::table::KeyedTable;
+pub use crate::tables::table::OrderedTable;
 pub use crate::tables::table::Table;
+pub use crate::tables::tracked_table::TrackedTable;
+pub use crate::tables::traits::has_key;
 pub use crate::tables::traits::has_rowid;
+pub use crate::tables::traits::row_id::RowId;
 
 pub use crate::datastructures::joins::*;
@@ -0,0 +1,154 @@
+use crate::Table;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::ByKey;
+use crate::has_key::HasKey;
+
+/// The differences between two snapshots of a [`Table`]. Created by [`Table::diff`].
+///
+/// All the rows are sorted by key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDiff<'t, R>
+where
+    R: HasKey,
+{
+    /// The rows of the new table that aren't in the old one
+    pub added: Vec<&'t R>,
+
+    /// The keys of the old table that aren't in the new one
+    pub removed: Vec<R::Key>,
+
+    /// The rows that are in both tables, but aren't equal. The first element is the old row, the second the new row.
+    pub changed: Vec<(&'t R, &'t R)>,
+}
+
+impl<'t, R> TableDiff<'t, R>
+where
+    R: HasKey,
+{
+    /// Return true if the two tables have the same rows
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn added_ids(&self) -> impl Iterator<Item = R::Key> + '_ {
+        self.added.iter().map(|row| row.key())
+    }
+
+    pub fn changed_ids(&self) -> impl Iterator<Item = R::Key> + '_ {
+        self.changed.iter().map(|(_, row)| row.key())
+    }
+
+    /// Apply the diff to a table, making it match the new snapshot for the rows of the diff.
+    ///
+    /// Added and changed rows are inserted, replacing the existing ones, and removed rows are removed.
+    pub fn apply_to<S>(&self, table: &mut Table<R, S>)
+    where
+        R: Clone,
+        S: RowStorage<R, Key = R::Key>,
+    {
+        for id in &self.removed {
+            table.remove(ByKey(id.clone()));
+        }
+
+        for row in &self.added {
+            table.insert((*row).clone());
+        }
+
+        for (_, row) in &self.changed {
+            table.insert((*row).clone());
+        }
+    }
+}
+
+impl<R, S> Table<R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    /// Compare this table with a newer snapshot of it. Rows are compared with [`PartialEq`]
+    pub fn diff<'t, S2>(&'t self, new: &'t Table<R, S2>) -> TableDiff<'t, R>
+    where
+        R: PartialEq,
+        S2: RowStorage<R, Key = R::Key>,
+    {
+        self.diff_by(new, |old, new| old == new)
+    }
+
+    /// Compare this table with a newer snapshot of it. Rows are equal if `eq` returns true
+    pub fn diff_by<'t, S2, F>(&'t self, new: &'t Table<R, S2>, mut eq: F) -> TableDiff<'t, R>
+    where
+        S2: RowStorage<R, Key = R::Key>,
+        F: FnMut(&R, &R) -> bool,
+    {
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+
+        for new_row in new.iter() {
+            match self.get(ByKey(new_row.key())) {
+                None => added.push(new_row),
+                Some(old_row) if !eq(old_row, new_row) => changed.push((old_row, new_row)),
+                Some(_) => {}
+            }
+        }
+
+        let mut removed = self
+            .iter()
+            .map(|row| row.key())
+            .filter(|id| new.get(ByKey(id.clone())).is_none())
+            .collect::<Vec<_>>();
+
+        added.sort_unstable_by_key(|row| row.key());
+        changed.sort_unstable_by_key(|(_, row)| row.key());
+        removed.sort_unstable();
+
+        TableDiff {
+            added,
+            removed,
+            changed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use crate::has_rowid::HasRowID;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Row {
+        id: i64,
+        name: &'static str,
+    }
+
+    impl HasRowID for Row {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[test]
+    fn diff_test() {
+        let old: Table<Row> = Table::from(vec![
+            Row { id: 1, name: "a" },
+            Row { id: 2, name: "b" },
+            Row { id: 3, name: "c" },
+        ]);
+        let new: Table<Row> = Table::from(vec![
+            Row { id: 1, name: "a" },
+            Row { id: 3, name: "C" },
+            Row { id: 4, name: "d" },
+        ]);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_ids().collect::<Vec<_>>(), [4]);
+        assert_eq!(diff.removed, [2]);
+        assert_eq!(diff.changed_ids().collect::<Vec<_>>(), [3]);
+
+        let mut cache: Table<Row> =
+            Table::from(vec![Row { id: 2, name: "b" }, Row { id: 3, name: "c" }]);
+        diff.apply_to(&mut cache);
+        assert_eq!(cache.get(3).unwrap().name, "C");
+        assert!(cache.get(2).is_none());
+        assert!(cache.get(4).is_some());
+    }
+}
@@ -0,0 +1,151 @@
+use futures::Stream;
+use futures::TryStreamExt as _;
+use futures::future;
+use sqlx::Executor;
+use sqlx::FromRow;
+use sqlx::IntoArguments;
+use sqlx::query::QueryAs;
+
+use crate::Persisted;
+use crate::Table;
+use crate::datastructures::storage::RowStorage;
+use crate::has_rowid::HasRowID;
+
+impl<R, S> Table<R, S>
+where
+    R: HasRowID,
+    S: RowStorage<R, Key = i64>,
+{
+    /// Run the query and insert the rows into a new table, as they are received.
+    ///
+    /// Unlike collecting the rows in a [`Vec`] first, only one copy of the rows is held in memory.
+    /// Rows with a rowid of 0 or less can't be put in a table, so they fail to decode
+    pub async fn fetch_all<'q, 'e, 'c: 'e, DB, A, E>(
+        query: QueryAs<'q, DB, R, A>,
+        executor: E,
+    ) -> Result<Self, sqlx::Error>
+    where
+        DB: sqlx::Database,
+        R: 'e + Send + Unpin + for<'r> FromRow<'r, DB::Row>,
+        A: 'q + IntoArguments<'q, DB>,
+        E: 'e + Executor<'c, Database = DB>,
+        'q: 'e,
+        DB: 'e,
+        A: 'e,
+    {
+        Self::try_from_stream(check_rowids(query.fetch(executor))).await
+    }
+
+    /// Insert the rows of a stream into a new table, as they are received
+    ///
+    /// # Panics
+    ///
+    /// Panics if a row doesn't have a valid rowid, like [`Table::insert`]
+    pub async fn try_from_stream<St, E>(stream: St) -> Result<Self, E>
+    where
+        St: Stream<Item = Result<R, E>>,
+    {
+        let mut table = Self::default();
+        table.try_extend_stream(stream).await?;
+        Ok(table)
+    }
+
+    /// Insert the rows of a stream into the table, as they are received. Rows with an existing rowid replace the old ones
+    ///
+    /// # Panics
+    ///
+    /// Panics if a row doesn't have a valid rowid, like [`Table::insert`]
+    pub async fn try_extend_stream<St, E>(&mut self, stream: St) -> Result<(), E>
+    where
+        St: Stream<Item = Result<R, E>>,
+    {
+        let mut stream = core::pin::pin!(stream);
+
+        while let Some(row) = stream.try_next().await? {
+            self.insert(row);
+        }
+
+        Ok(())
+    }
+}
+
+/// Turn the rows that can't be put in a table into decode errors
+pub(crate) fn check_rowids<R, St>(stream: St) -> impl Stream<Item = Result<R, sqlx::Error>>
+where
+    R: HasRowID,
+    St: Stream<Item = Result<R, sqlx::Error>>,
+{
+    stream.and_then(|row| {
+        future::ready(match Persisted::new(row) {
+            Ok(row) => Ok(row.into_inner()),
+            Err(err) => Err(sqlx::Error::Decode(Box::new(err.without_row()))),
+        })
+    })
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+    use futures::stream;
+    use sqlx::FromRow;
+    use sqlx::SqlitePool;
+
+    use crate::Table;
+    use crate::has_rowid::HasRowID;
+
+    #[derive(Debug, FromRow)]
+    struct Listen {
+        id: i64,
+        user: String,
+    }
+
+    impl HasRowID for Listen {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_all_test() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql("CREATE TABLE listens (id INTEGER PRIMARY KEY, user TEXT NOT NULL); INSERT INTO listens VALUES (1, 'alice'), (2, 'bob');")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let listens: Table<Listen> =
+            Table::fetch_all(sqlx::query_as("SELECT * FROM listens"), &pool)
+                .await
+                .unwrap();
+        assert_eq!(listens.len(), 2);
+        assert_eq!(listens.get(2).unwrap().user, "bob");
+
+        let missing: Result<Table<Listen>, _> =
+            Table::fetch_all(sqlx::query_as("SELECT * FROM missing"), &pool).await;
+        assert!(missing.is_err());
+
+        // Unsaved rows can't be put in a table
+        let unsaved: Result<Table<Listen>, _> =
+            Table::fetch_all(sqlx::query_as("SELECT 0 AS id, 'eve' AS user"), &pool).await;
+        assert!(matches!(unsaved, Err(sqlx::Error::Decode(_))));
+    }
+
+    #[tokio::test]
+    async fn try_extend_stream_test() {
+        let mut table: Table<i64> = Table::try_from_stream(stream::iter([Ok::<_, &str>(1), Ok(2)]))
+            .await
+            .unwrap();
+        table
+            .try_extend_stream(stream::iter([Ok::<_, &str>(2), Ok(3)]))
+            .await
+            .unwrap();
+        assert_eq!(table.len(), 3);
+
+        // The rows received before the error are kept
+        let result = table
+            .try_extend_stream(stream::iter([Ok(4), Err("broken"), Ok(5)]))
+            .await;
+        assert_eq!(result, Err("broken"));
+        assert!(table.get(4).is_some());
+        assert!(table.get(5).is_none());
+    }
+}
@@ -0,0 +1,489 @@
+use core::any::Any;
+use core::hash::Hash;
+use core::marker::PhantomData;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+use std::collections::HashMap;
+
+use snafu::Snafu;
+
+use crate::Table;
+use crate::datastructures::as_hashmap::AsHashMap;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::has_key::IntoKey;
+use crate::has_key::assert_valid_key;
+use crate::has_rowid::HasRowID;
+
+/// A [`Table`] with secondary indexes, allowing to get rows by other keys than their rowid.
+///
+/// The indexes are kept up to date on insertion, removal, and [`IndexedTable::update`].
+///
+/// # Exemple
+/// ```
+/// # use sequelles::IndexedTable;
+/// # use sequelles::has_rowid::HasRowID;
+/// struct Recording {
+///     id: i64,
+///     mbid: String,
+///     artist: String,
+/// }
+/// # impl HasRowID for Recording {
+/// #     fn rowid(&self) -> i64 {
+/// #         self.id
+/// #     }
+/// # }
+///
+/// let mut recordings = IndexedTable::new();
+/// let by_mbid = recordings.add_unique_index(|rec: &Recording| rec.mbid.clone()).unwrap();
+/// let by_artist = recordings.add_index(|rec: &Recording| rec.artist.clone());
+///
+/// recordings.insert(Recording { id: 1, mbid: "a".to_string(), artist: "Artist".to_string() }).unwrap();
+/// recordings.insert(Recording { id: 2, mbid: "b".to_string(), artist: "Artist".to_string() }).unwrap();
+///
+/// assert_eq!(recordings.get_unique(by_mbid, &"b".to_string()).unwrap().id, 2);
+/// assert_eq!(recordings.get_indexed(by_artist, &"Artist".to_string()).count(), 2);
+/// ```
+pub struct IndexedTable<R> {
+    /// The id of the table, so index ids of other tables can be told apart
+    id: u64,
+    table: Table<R>,
+    indexes: Vec<Box<dyn SecondaryIndex<R>>>,
+}
+
+/// Give an id to each indexed table, as index ids are only positions in their table
+fn next_table_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+impl<R> IndexedTable<R>
+where
+    R: HasRowID + 'static,
+{
+    /// Create a new table without indexes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an index where each key points to a single row. Existing rows are indexed.
+    ///
+    /// Returns an error if two rows of the table already share the same key
+    pub fn add_unique_index<K, F>(&mut self, key: F) -> Result<UniqueIndexId<K>, UniqueIndexError>
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        F: Fn(&R) -> K + Send + Sync + 'static,
+    {
+        let mut index = UniqueIndex {
+            key: Box::new(key),
+            map: HashMap::with_capacity(self.table.len()),
+        };
+
+        for row in self.table.iter() {
+            index.check(row)?;
+            index.insert(row);
+        }
+
+        self.indexes.push(Box::new(index));
+        Ok(UniqueIndexId {
+            table: self.id,
+            index: self.indexes.len() - 1,
+            _key: PhantomData,
+        })
+    }
+
+    /// Add an index where each key can point to multiple rows. Existing rows are indexed.
+    pub fn add_index<K, F>(&mut self, key: F) -> IndexId<K>
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        F: Fn(&R) -> K + Send + Sync + 'static,
+    {
+        let mut index = MultiIndex {
+            key: Box::new(key),
+            map: HashMap::new(),
+        };
+
+        for row in self.table.iter() {
+            index.insert(row);
+        }
+
+        self.indexes.push(Box::new(index));
+        IndexId {
+            table: self.id,
+            index: self.indexes.len() - 1,
+            _key: PhantomData,
+        }
+    }
+
+    /// Insert a new row in the table, returning the row it replaced.
+    ///
+    /// Returns an error, and doesn't insert the row, if it would break a unique index
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row doesn't have a valid rowid, like an unsaved row with the rowid 0
+    #[track_caller]
+    pub fn insert(&mut self, value: R) -> Result<Option<R>, UniqueIndexError> {
+        assert_valid_key(&value);
+        for index in &self.indexes {
+            index.check(&value)?;
+        }
+
+        let old = self.remove(value.rowid());
+
+        for index in &mut self.indexes {
+            index.insert(&value);
+        }
+        self.table.insert(value);
+
+        Ok(old)
+    }
+
+    /// Get a row by its rowid
+    pub fn get<I>(&self, key: I) -> Option<&R>
+    where
+        I: IntoKey<R>,
+    {
+        self.table.get(key)
+    }
+
+    /// Modify a row by its rowid, and index it again. Returns the result of `f`, or `None` if there is no such row.
+    ///
+    /// Returns an error if the modified row would break a unique index. The row is then restored to its previous value.
+    /// `f` must not change the rowid of the row
+    pub fn update<I, F, T>(&mut self, key: I, f: F) -> Result<Option<T>, UniqueIndexError>
+    where
+        I: IntoKey<R>,
+        F: FnOnce(&mut R) -> T,
+        R: Clone,
+    {
+        let Some(row) = self.table.get_mut(key) else {
+            return Ok(None);
+        };
+        let old = row.clone();
+
+        for index in &mut self.indexes {
+            index.remove(row);
+        }
+
+        let result = f(row);
+        debug_assert_eq!(row.rowid(), old.rowid(), "The rowid of the row changed");
+
+        // Check every index before touching them, so a conflict leaves them all as they were
+        let checked = self.indexes.iter().try_for_each(|index| index.check(row));
+        if checked.is_err() {
+            *row = old;
+        }
+
+        for index in &mut self.indexes {
+            index.insert(row);
+        }
+
+        checked.map(|()| Some(result))
+    }
+
+    /// Remove a row from the table
+    pub fn remove<I>(&mut self, key: I) -> Option<R>
+    where
+        I: IntoKey<R>,
+    {
+        let row = self.table.remove(key)?;
+
+        for index in &mut self.indexes {
+            index.remove(&row);
+        }
+
+        Some(row)
+    }
+
+    /// Get the row associated to a key of a unique index.
+    ///
+    /// Returns `None` if the index id was created by another table
+    pub fn get_unique<K>(&self, index: UniqueIndexId<K>, key: &K) -> Option<&R>
+    where
+        K: Eq + Hash + 'static,
+    {
+        self.get_index::<UniqueIndex<R, K>>(index.table, index.index)?
+            .map
+            .get(key)
+            .and_then(|id| self.table.get(id))
+    }
+
+    /// Get all the rows associated to a key of an index.
+    ///
+    /// Returns no rows if the index id was created by another table
+    pub fn get_indexed<K>(&self, index: IndexId<K>, key: &K) -> impl Iterator<Item = &R>
+    where
+        K: Eq + Hash + 'static,
+    {
+        self.get_index::<MultiIndex<R, K>>(index.table, index.index)
+            .and_then(|index| index.map.get(key))
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.table.get(id))
+    }
+
+    /// Get an index from the parts of its id, checking that it belongs to this table
+    fn get_index<T>(&self, table: u64, index: usize) -> Option<&T>
+    where
+        T: 'static,
+    {
+        if table != self.id {
+            return None;
+        }
+
+        self.indexes.get(index)?.as_any().downcast_ref::<T>()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &R> {
+        self.table.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Return the underlying table
+    pub fn as_table(&self) -> &Table<R> {
+        &self.table
+    }
+
+    /// Drop the indexes and return the underlying table
+    pub fn into_table(self) -> Table<R> {
+        self.table
+    }
+}
+
+/// There is no mutable access, as the indexes would get out of sync
+impl<R> AsHashMap<i64, R> for IndexedTable<R>
+where
+    R: HasRowID,
+{
+    fn as_hashmap(&self) -> &HashMap<i64, R, BuildRowIdHasher> {
+        self.table.as_hashmap()
+    }
+}
+
+impl<R> Default for IndexedTable<R> {
+    fn default() -> Self {
+        Self {
+            id: next_table_id(),
+            table: Table::default(),
+            indexes: Vec::new(),
+        }
+    }
+}
+
+impl<R> From<Table<R>> for IndexedTable<R> {
+    fn from(table: Table<R>) -> Self {
+        Self {
+            id: next_table_id(),
+            table,
+            indexes: Vec::new(),
+        }
+    }
+}
+
+impl<R> IntoIterator for IndexedTable<R> {
+    type Item = R;
+    type IntoIter = <Table<R> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.table.into_iter()
+    }
+}
+
+/// A handle to a unique index of an [`IndexedTable`]. It only refers to an index of the table that created it
+pub struct UniqueIndexId<K> {
+    table: u64,
+    index: usize,
+    _key: PhantomData<fn() -> K>,
+}
+
+/// A handle to a non unique index of an [`IndexedTable`]. It only refers to an index of the table that created it
+pub struct IndexId<K> {
+    table: u64,
+    index: usize,
+    _key: PhantomData<fn() -> K>,
+}
+
+// Manual impls, as deriving would require `K: Clone`
+impl<K> Clone for UniqueIndexId<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for UniqueIndexId<K> {}
+
+impl<K> Clone for IndexId<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for IndexId<K> {}
+
+#[derive(Debug, Snafu, Clone, PartialEq, Eq)]
+#[snafu(display("The row {rowid} has the same unique key as the row {existing}"))]
+pub struct UniqueIndexError {
+    /// The rowid of the row that couldn't be indexed
+    pub rowid: i64,
+
+    /// The rowid of the row already having the key
+    pub existing: i64,
+}
+
+trait SecondaryIndex<R>: Send + Sync {
+    /// Check if the row can be inserted in the index
+    fn check(&self, row: &R) -> Result<(), UniqueIndexError>;
+
+    fn insert(&mut self, row: &R);
+
+    fn remove(&mut self, row: &R);
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+type KeyFn<R, K> = Box<dyn Fn(&R) -> K + Send + Sync>;
+
+struct UniqueIndex<R, K> {
+    key: KeyFn<R, K>,
+    map: HashMap<K, i64>,
+}
+
+impl<R, K> SecondaryIndex<R> for UniqueIndex<R, K>
+where
+    R: HasRowID + 'static,
+    K: Eq + Hash + Send + Sync + 'static,
+{
+    fn check(&self, row: &R) -> Result<(), UniqueIndexError> {
+        match self.map.get(&(self.key)(row)) {
+            Some(&existing) if existing != row.rowid() => Err(UniqueIndexError {
+                rowid: row.rowid(),
+                existing,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn insert(&mut self, row: &R) {
+        self.map.insert((self.key)(row), row.rowid());
+    }
+
+    fn remove(&mut self, row: &R) {
+        let key = (self.key)(row);
+
+        if self.map.get(&key) == Some(&row.rowid()) {
+            self.map.remove(&key);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+struct MultiIndex<R, K> {
+    key: KeyFn<R, K>,
+    map: HashMap<K, Vec<i64>>,
+}
+
+impl<R, K> SecondaryIndex<R> for MultiIndex<R, K>
+where
+    R: HasRowID + 'static,
+    K: Eq + Hash + Send + Sync + 'static,
+{
+    fn check(&self, _row: &R) -> Result<(), UniqueIndexError> {
+        Ok(())
+    }
+
+    fn insert(&mut self, row: &R) {
+        self.map
+            .entry((self.key)(row))
+            .or_default()
+            .push(row.rowid());
+    }
+
+    fn remove(&mut self, row: &R) {
+        let key = (self.key)(row);
+
+        if let Some(ids) = self.map.get_mut(&key) {
+            ids.retain(|id| *id != row.rowid());
+
+            if ids.is_empty() {
+                self.map.remove(&key);
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::IndexedTable;
+    use crate::has_rowid::HasRowID;
+
+    #[derive(Clone)]
+    struct Listen {
+        id: i64,
+        user: &'static str,
+    }
+
+    impl HasRowID for Listen {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[test]
+    fn indexed_table_test() {
+        let mut listens = IndexedTable::new();
+        let by_user = listens.add_index(|listen: &Listen| listen.user);
+        let unique_user = listens
+            .add_unique_index(|listen: &Listen| listen.user)
+            .unwrap();
+
+        listens
+            .insert(Listen {
+                id: 1,
+                user: "alice",
+            })
+            .unwrap();
+        listens.insert(Listen { id: 2, user: "bob" }).unwrap();
+        assert!(listens.insert(Listen { id: 3, user: "bob" }).is_err());
+        assert_eq!(listens.len(), 2);
+
+        listens.update(2, |listen| listen.user = "carol").unwrap();
+        assert_eq!(listens.get_indexed(by_user, &"bob").count(), 0);
+        assert_eq!(listens.get_unique(unique_user, &"carol").unwrap().id, 2);
+
+        // A conflicting update is rolled back, in all the indexes
+        assert!(listens.update(2, |listen| listen.user = "alice").is_err());
+        assert_eq!(listens.get(2).unwrap().user, "carol");
+        assert_eq!(listens.get_indexed(by_user, &"carol").count(), 1);
+        assert_eq!(listens.get_unique(unique_user, &"alice").unwrap().id, 1);
+        assert_eq!(listens.update(4, |_| ()), Ok(None));
+
+        // Index ids of another table don't refer to this table's indexes
+        let mut other = IndexedTable::<Listen>::new();
+        let other_by_user = other.add_index(|listen: &Listen| listen.user);
+        assert_eq!(listens.get_indexed(other_by_user, &"carol").count(), 0);
+
+        listens.remove(1);
+        assert!(listens.get_unique(unique_user, &"alice").is_none());
+        assert_eq!(listens.get_indexed(by_user, &"alice").count(), 0);
+    }
+
+    #[test]
+    fn indexed_table_send_sync_test() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<IndexedTable<Listen>>();
+    }
+}
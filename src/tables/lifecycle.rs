@@ -0,0 +1,363 @@
+use core::ops::Deref;
+use core::ops::DerefMut;
+
+use snafu::Snafu;
+use snafu::ensure;
+use sqlx::FromRow;
+
+use crate::RowIdSet;
+use crate::Table;
+use crate::has_rowid::HasRowID;
+use crate::has_rowid::SetRowID;
+
+/// A row that isn't inserted in the database yet, so it doesn't have a rowid.
+///
+/// It doesn't implement [`HasRowID`], so it can't be put in a [`Table`] or a join. Use a [`StagingTable`] to hold them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct NewRow<T>(T);
+
+impl<T> NewRow<T> {
+    pub fn new(row: T) -> Self {
+        Self(row)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    /// Give the row the rowid assigned by the database.
+    ///
+    /// If the rowid is invalid, the row is given back in the error
+    pub fn persist(mut self, rowid: i64) -> Result<Persisted<T>, NotPersistedError<Self>>
+    where
+        T: SetRowID,
+    {
+        ensure!(rowid > 0, NotPersistedSnafu { rowid, row: self });
+
+        self.0.set_rowid(rowid);
+        Ok(Persisted(self.0))
+    }
+}
+
+impl<T> Deref for NewRow<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for NewRow<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// A row that exists in the database, so its rowid is above 0.
+///
+/// Use `Table<Persisted<T>>` for tables that must never hold unsaved rows.
+/// It can be fetched directly with `query_as`, and fails to decode rows without a valid rowid.
+///
+/// The row can only be modified with [`Persisted::update`], which checks that it keeps its rowid.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Persisted<T>(T);
+
+impl<T> Persisted<T>
+where
+    T: HasRowID,
+{
+    /// Check that the row has a valid rowid. If it doesn't, the row is given back in the error
+    pub fn new(row: T) -> Result<Self, NotPersistedError<T>> {
+        ensure!(
+            row.rowid() > 0,
+            NotPersistedSnafu {
+                rowid: row.rowid(),
+                row
+            }
+        );
+
+        Ok(Self(row))
+    }
+
+    /// Modify the row, returning the result of `f`.
+    ///
+    /// Returns an error if `f` changed the rowid, as the row would be out of place in its table. Its rowid is then restored
+    pub fn update<F, U>(&mut self, f: F) -> Result<U, RowidChangedError>
+    where
+        F: FnOnce(&mut T) -> U,
+        T: SetRowID,
+    {
+        let rowid = self.0.rowid();
+        let result = f(&mut self.0);
+
+        let new_rowid = self.0.rowid();
+        if new_rowid != rowid {
+            self.0.set_rowid(rowid);
+            return RowidChangedSnafu { rowid, new_rowid }.fail();
+        }
+
+        Ok(result)
+    }
+}
+
+impl<T> Persisted<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> HasRowID for Persisted<T>
+where
+    T: HasRowID,
+{
+    fn rowid(&self) -> i64 {
+        self.0.rowid()
+    }
+}
+
+impl<T> Deref for Persisted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'r, T, DbRow> FromRow<'r, DbRow> for Persisted<T>
+where
+    T: HasRowID + FromRow<'r, DbRow>,
+    DbRow: sqlx::Row,
+{
+    fn from_row(row: &'r DbRow) -> Result<Self, sqlx::Error> {
+        Self::new(T::from_row(row)?).map_err(|err| sqlx::Error::Decode(Box::new(err.without_row())))
+    }
+}
+
+/// A collection of rows waiting to be inserted in the database.
+///
+/// Once inserted, give them their rowids with [`StagingTable::into_table`]
+#[derive(Debug, Clone)]
+pub struct StagingTable<T> {
+    rows: Vec<NewRow<T>>,
+}
+
+impl<T> StagingTable<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a row to insert
+    pub fn push(&mut self, row: T) {
+        self.rows.push(NewRow::new(row));
+    }
+
+    /// Iterate over the rows, in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.rows.iter().map(Deref::deref)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.rows.iter_mut().map(DerefMut::deref_mut)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Give the rows the rowids assigned by the database, in insertion order, and put them in a [`Table`].
+    ///
+    /// The rowids are checked before any row is persisted. They must all be valid and different.
+    /// On error, the staged rows are given back in the error
+    pub fn into_table<I>(self, rowids: I) -> Result<Table<Persisted<T>>, StagingError<T>>
+    where
+        T: SetRowID,
+        I: IntoIterator<Item = i64>,
+    {
+        let rowids = rowids.into_iter().collect::<Vec<_>>();
+
+        ensure!(
+            rowids.len() == self.rows.len(),
+            RowidCountSnafu {
+                expected: self.rows.len(),
+                got: rowids.len(),
+                rows: self,
+            }
+        );
+        if let Some(&rowid) = rowids.iter().find(|rowid| **rowid <= 0) {
+            return InvalidRowidSnafu { rowid, rows: self }.fail();
+        }
+        let mut seen = RowIdSet::default();
+        if let Some(&rowid) = rowids.iter().find(|rowid| !seen.insert(**rowid)) {
+            return DuplicateRowidSnafu { rowid, rows: self }.fail();
+        }
+
+        let mut table = Table::new();
+        for (mut row, rowid) in self.rows.into_iter().zip(rowids) {
+            row.0.set_rowid(rowid);
+            table.insert(Persisted(row.0));
+        }
+
+        Ok(table)
+    }
+}
+
+impl<T> Default for StagingTable<T> {
+    fn default() -> Self {
+        Self { rows: Vec::new() }
+    }
+}
+
+impl<T> FromIterator<T> for StagingTable<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            rows: iter.into_iter().map(NewRow::new).collect(),
+        }
+    }
+}
+
+impl<T> IntoIterator for StagingTable<T> {
+    type Item = NewRow<T>;
+    type IntoIter = std::vec::IntoIter<NewRow<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+/// The error of a row that doesn't have a valid rowid. It holds the row, so it isn't lost
+#[derive(Debug, Snafu)]
+#[snafu(display("The rowid {rowid} isn't the rowid of a row in the database"))]
+pub struct NotPersistedError<T = ()> {
+    pub rowid: i64,
+    pub row: T,
+}
+
+impl<T> NotPersistedError<T> {
+    /// Drop the row, to only keep the error
+    pub fn without_row(self) -> NotPersistedError {
+        NotPersistedError {
+            rowid: self.rowid,
+            row: (),
+        }
+    }
+}
+
+/// The error of [`Persisted::update`], when the rowid of the row was changed
+#[derive(Debug, Snafu, Clone, PartialEq, Eq)]
+#[snafu(display("The rowid {rowid} of a persisted row can't be changed to {new_rowid}"))]
+pub struct RowidChangedError {
+    pub rowid: i64,
+    pub new_rowid: i64,
+}
+
+/// The error of [`StagingTable::into_table`]. It holds the staged rows, so they aren't lost
+#[derive(Debug, Snafu)]
+pub enum StagingError<T> {
+    #[snafu(display("Expected {expected} rowids for the staged rows, but got {got}"))]
+    RowidCountError {
+        expected: usize,
+        got: usize,
+        rows: StagingTable<T>,
+    },
+
+    #[snafu(display("A staged row got the invalid rowid {rowid}"))]
+    InvalidRowidError { rowid: i64, rows: StagingTable<T> },
+
+    #[snafu(display("Several staged rows got the rowid {rowid}"))]
+    DuplicateRowidError { rowid: i64, rows: StagingTable<T> },
+}
+
+impl<T> StagingError<T> {
+    /// Get back the staged rows
+    pub fn into_rows(self) -> StagingTable<T> {
+        match self {
+            Self::RowidCountError { rows, .. }
+            | Self::InvalidRowidError { rows, .. }
+            | Self::DuplicateRowidError { rows, .. } => rows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::has_rowid::HasRowID;
+    use crate::has_rowid::SetRowID;
+    use crate::tables::lifecycle::NewRow;
+    use crate::tables::lifecycle::RowidChangedError;
+    use crate::tables::lifecycle::StagingError;
+    use crate::tables::lifecycle::StagingTable;
+
+    #[derive(Debug)]
+    struct User {
+        id: i64,
+        name: &'static str,
+    }
+
+    impl HasRowID for User {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    impl SetRowID for User {
+        fn set_rowid(&mut self, rowid: i64) {
+            self.id = rowid;
+        }
+    }
+
+    #[test]
+    fn staging_test() {
+        let staged = || {
+            StagingTable::from_iter([
+                User {
+                    id: 0,
+                    name: "Alice",
+                },
+                User { id: 0, name: "Bob" },
+            ])
+        };
+
+        assert!(staged().into_table([1]).is_err());
+        let err = staged().into_table([5, 5]).err().unwrap();
+        assert!(matches!(
+            err,
+            StagingError::DuplicateRowidError { rowid: 5, .. }
+        ));
+        assert_eq!(err.into_rows().len(), 2);
+
+        let rows = staged().into_table([1, 0]).err().unwrap().into_rows();
+        assert_eq!(
+            rows.iter().map(|user| user.name).collect::<Vec<_>>(),
+            ["Alice", "Bob"]
+        );
+        assert_eq!(rows.iter().map(|user| user.id).collect::<Vec<_>>(), [0, 0]);
+
+        let mut users = staged().into_table([5, 6]).unwrap();
+        assert_eq!(users.get(6).unwrap().name, "Bob");
+
+        let bob = users.get_mut(6).unwrap();
+        assert!(bob.update(|user| user.id = 0).is_err());
+        assert_eq!(
+            bob.update(|user| user.id = 9),
+            Err(RowidChangedError {
+                rowid: 6,
+                new_rowid: 9
+            })
+        );
+        assert_eq!(bob.rowid(), 6);
+        bob.update(|user| user.name = "Robert").unwrap();
+        assert_eq!(users.get(6).unwrap().name, "Robert");
+
+        let err = NewRow::new(User {
+            id: 0,
+            name: "Carol",
+        })
+        .persist(-1)
+        .unwrap_err();
+        assert_eq!(err.row.name, "Carol");
+    }
+}
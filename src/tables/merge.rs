@@ -0,0 +1,277 @@
+use crate::Table;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::ByKey;
+use crate::has_key::HasKey;
+
+/// Decide what to do when a row is inserted in a [`Table`] that already has a row with the same rowid.
+///
+/// Implemented by [`KeepExisting`], [`Replace`], and any `FnMut(&mut R, R)` closure, which merges the new row into the existing one.
+pub trait ConflictPolicy<R> {
+    /// Resolve the conflict between the row in the table and the new one
+    fn resolve(&mut self, existing: &mut R, new: R) -> Resolution<R>;
+}
+
+/// How a [`ConflictPolicy`] resolved a conflict
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution<R> {
+    /// The existing row was kept. This holds the discarded new row
+    Kept(R),
+
+    /// The existing row was replaced. This holds the old row
+    Replaced(R),
+
+    /// The new row was merged into the existing one
+    Merged,
+}
+
+/// Keep the row already in the table, and discard the new one
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeepExisting;
+
+impl<R> ConflictPolicy<R> for KeepExisting {
+    fn resolve(&mut self, _existing: &mut R, new: R) -> Resolution<R> {
+        Resolution::Kept(new)
+    }
+}
+
+/// Replace the row already in the table with the new one. This is what [`Table::insert`] does
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Replace;
+
+impl<R> ConflictPolicy<R> for Replace {
+    fn resolve(&mut self, existing: &mut R, new: R) -> Resolution<R> {
+        Resolution::Replaced(core::mem::replace(existing, new))
+    }
+}
+
+impl<R, F> ConflictPolicy<R> for F
+where
+    F: FnMut(&mut R, R),
+{
+    fn resolve(&mut self, existing: &mut R, new: R) -> Resolution<R> {
+        self(existing, new);
+        Resolution::Merged
+    }
+}
+
+/// What happened to a row inserted with [`Table::upsert_with`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpsertOutcome<R> {
+    /// There was no row with this rowid, so it got inserted
+    Inserted,
+
+    /// There was a row with this rowid. The conflict got resolved by the policy
+    Conflict(Resolution<R>),
+}
+
+/// The rowids, or keys, affected by [`Table::extend_with`], by outcome
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeReport<K = i64> {
+    pub inserted: Vec<K>,
+    pub kept: Vec<K>,
+    pub replaced: Vec<K>,
+    pub merged: Vec<K>,
+}
+
+impl<K> MergeReport<K> {
+    /// Return true if no rowid had a conflict
+    pub fn is_conflict_free(&self) -> bool {
+        self.kept.is_empty() && self.replaced.is_empty() && self.merged.is_empty()
+    }
+
+    fn record<R>(&mut self, key: K, outcome: &UpsertOutcome<R>) {
+        let ids = match outcome {
+            UpsertOutcome::Inserted => &mut self.inserted,
+            UpsertOutcome::Conflict(Resolution::Kept(_)) => &mut self.kept,
+            UpsertOutcome::Conflict(Resolution::Replaced(_)) => &mut self.replaced,
+            UpsertOutcome::Conflict(Resolution::Merged) => &mut self.merged,
+        };
+
+        ids.push(key);
+    }
+}
+
+// Manual impl, as deriving would require `K: Default`
+impl<K> Default for MergeReport<K> {
+    fn default() -> Self {
+        Self {
+            inserted: Vec::new(),
+            kept: Vec::new(),
+            replaced: Vec::new(),
+            merged: Vec::new(),
+        }
+    }
+}
+
+impl<R, S> Table<R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    /// Insert a row, resolving conflicts with an existing row using the given policy
+    pub fn upsert_with<P>(&mut self, row: R, mut policy: P) -> UpsertOutcome<R>
+    where
+        P: ConflictPolicy<R>,
+    {
+        self.upsert_with_mut(row, &mut policy)
+    }
+
+    fn upsert_with_mut<P>(&mut self, row: R, policy: &mut P) -> UpsertOutcome<R>
+    where
+        P: ConflictPolicy<R>,
+    {
+        match self.get_mut(ByKey(row.key())) {
+            Some(existing) => UpsertOutcome::Conflict(policy.resolve(existing, row)),
+            None => {
+                self.insert(row);
+                UpsertOutcome::Inserted
+            }
+        }
+    }
+
+    /// Insert a row, replacing the existing one if any. Unlike [`Table::insert`], the old row is returned
+    pub fn upsert(&mut self, row: R) -> UpsertOutcome<R> {
+        self.upsert_with(row, Replace)
+    }
+
+    /// Insert a row, merging it into the existing one if any
+    pub fn merge<F>(&mut self, row: R, merge: F) -> UpsertOutcome<R>
+    where
+        F: FnMut(&mut R, R),
+    {
+        self.upsert_with(row, merge)
+    }
+
+    /// Insert all the rows of the iterator, resolving conflicts using the given policy.
+    ///
+    /// The conflicts between the rows of the iterator are resolved the same way.
+    pub fn extend_with<I, P>(&mut self, rows: I, mut policy: P) -> MergeReport<R::Key>
+    where
+        I: IntoIterator<Item = R>,
+        P: ConflictPolicy<R>,
+    {
+        let mut report = MergeReport::default();
+
+        for row in rows {
+            let key = row.key();
+            let outcome = self.upsert_with_mut(row, &mut policy);
+            report.record(key, &outcome);
+        }
+
+        report
+    }
+
+    /// Merge another table into this one, resolving conflicts using the given policy
+    pub fn merge_table<S2, P>(&mut self, other: Table<R, S2>, policy: P) -> MergeReport<R::Key>
+    where
+        S2: RowStorage<R, Key = R::Key>,
+        P: ConflictPolicy<R>,
+    {
+        self.extend_with(other, policy)
+    }
+
+    /// Create a table from an iterator, resolving duplicate rowids using the given policy
+    pub fn from_iter_with<I, P>(rows: I, policy: P) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        P: ConflictPolicy<R>,
+    {
+        let mut table = Self::default();
+        table.extend_with(rows, policy);
+        table
+    }
+}
+
+/// Rows with an existing rowid replace the old ones, like [`Table::insert`]
+impl<R, S> Extend<R> for Table<R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    fn extend<I: IntoIterator<Item = R>>(&mut self, iter: I) {
+        for row in iter {
+            self.insert(row);
+        }
+    }
+}
+
+/// Rows with a duplicate rowid replace the previous ones, like [`Table::insert`]
+impl<R, S> FromIterator<R> for Table<R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    fn from_iter<I: IntoIterator<Item = R>>(iter: I) -> Self {
+        let mut table = Self::default();
+        table.extend(iter);
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use crate::has_rowid::HasRowID;
+    use crate::tables::merge::KeepExisting;
+    use crate::tables::merge::Resolution;
+    use crate::tables::merge::UpsertOutcome;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Recording {
+        id: i64,
+        title: Option<&'static str>,
+        length: Option<u32>,
+    }
+
+    impl HasRowID for Recording {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[test]
+    fn merge_test() {
+        let titles = vec![Recording {
+            id: 1,
+            title: Some("Title"),
+            length: None,
+        }];
+        let lengths = vec![
+            Recording {
+                id: 1,
+                title: None,
+                length: Some(180),
+            },
+            Recording {
+                id: 2,
+                title: None,
+                length: Some(200),
+            },
+        ];
+
+        let mut table: Table<Recording> = titles.into_iter().collect();
+        let report = table.extend_with(
+            lengths.clone(),
+            |existing: &mut Recording, new: Recording| {
+                existing.length = existing.length.or(new.length);
+            },
+        );
+        assert_eq!(report.inserted, [2]);
+        assert_eq!(report.merged, [1]);
+        assert_eq!(table.get(1).unwrap().title, Some("Title"));
+        assert_eq!(table.get(1).unwrap().length, Some(180));
+
+        let outcome = table.upsert_with(lengths[0].clone(), KeepExisting);
+        assert_eq!(
+            outcome,
+            UpsertOutcome::Conflict(Resolution::Kept(lengths[0].clone()))
+        );
+
+        let outcome = table.upsert(lengths[0].clone());
+        assert!(matches!(
+            outcome,
+            UpsertOutcome::Conflict(Resolution::Replaced(_))
+        ));
+        assert_eq!(table.get(1).unwrap().title, None);
+    }
+}
@@ -1,2 +1,12 @@
+pub mod diff;
+pub mod fetch;
+pub mod indexed_table;
+pub mod lifecycle;
+pub mod merge;
+pub mod paginate;
+pub mod query;
+pub mod shared_table;
+pub mod strict;
 pub mod table;
+pub mod tracked_table;
 pub mod traits;
@@ -0,0 +1,256 @@
+use core::marker::PhantomData;
+use std::collections::HashMap;
+
+use futures::Stream;
+use futures::StreamExt as _;
+use futures::TryStreamExt as _;
+use futures::stream;
+use sqlx::Encode;
+use sqlx::Executor;
+use sqlx::FromRow;
+use sqlx::IntoArguments;
+use sqlx::Type;
+
+use crate::Table;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::storage::RowStorage;
+use crate::has_rowid::HasRowID;
+use crate::tables::fetch::check_rowids;
+
+/// Fetch the rows of a query page by page, using the rowids as cursor (keyset pagination).
+///
+/// The query gets the last rowid of the previous page as its first bind parameter, and the page size as the second.
+/// It must only return the rows after that rowid, in rowid order:
+/// `SELECT * FROM listens WHERE id > ? ORDER BY id LIMIT ?`
+///
+/// The data is exhausted once the query returns less rows than the page size. Save [`Paginator::cursor`] to resume the pagination later.
+/// A paginator with a page size of 0 is exhausted from the start
+///
+/// # Exemple
+#[cfg_attr(feature = "sqlite", doc = "```")]
+#[cfg_attr(not(feature = "sqlite"), doc = "```ignore")]
+/// # use futures::TryStreamExt as _;
+/// # use sequelles::Paginator;
+/// # use sqlx::sqlite::SqlitePool;
+/// # #[derive(sqlx::FromRow)]
+/// # struct Listen {
+/// #     id: i64,
+/// # }
+/// # impl sequelles::has_rowid::HasRowID for Listen {
+/// #     fn rowid(&self) -> i64 {
+/// #         self.id
+/// #     }
+/// # }
+/// # tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(async {
+/// # let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+/// # sqlx::raw_sql("CREATE TABLE listens (id INTEGER PRIMARY KEY); INSERT INTO listens VALUES (1), (2), (3);")
+/// #     .execute(&pool)
+/// #     .await
+/// #     .unwrap();
+/// let paginator = Paginator::<Listen>::new("SELECT * FROM listens WHERE id > ? ORDER BY id LIMIT ?", 2);
+/// let pages = paginator.into_stream(&pool).try_collect::<Vec<_>>().await.unwrap();
+///
+/// assert_eq!(pages.len(), 2);
+/// assert_eq!(pages[1].len(), 1);
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Paginator<'q, R, S = HashMap<i64, R, BuildRowIdHasher>> {
+    sql: &'q str,
+    limit: u32,
+    cursor: i64,
+    exhausted: bool,
+
+    _table: PhantomData<fn() -> Table<R, S>>,
+}
+
+impl<'q, R, S> Paginator<'q, R, S>
+where
+    R: HasRowID,
+    S: RowStorage<R, Key = i64>,
+{
+    /// Create a paginator starting at the first row, including the rows with a negative rowid
+    pub fn new(sql: &'q str, limit: u32) -> Self {
+        Self::resume(sql, limit, i64::MIN)
+    }
+
+    /// Create a paginator starting after the rowid `cursor`, like one saved from [`Paginator::cursor`]
+    pub fn resume(sql: &'q str, limit: u32, cursor: i64) -> Self {
+        Self {
+            sql,
+            limit,
+            cursor,
+            exhausted: limit == 0,
+            _table: PhantomData,
+        }
+    }
+
+    /// The rowid of the last row fetched. The next page starts after it
+    pub fn cursor(&self) -> i64 {
+        self.cursor
+    }
+
+    /// Whether the last page has been fetched
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Fetch the next page, or `None` if the data is exhausted
+    pub async fn next_page<'e, 'c: 'e, DB, E>(
+        &mut self,
+        executor: E,
+    ) -> Result<Option<Table<R, S>>, sqlx::Error>
+    where
+        DB: sqlx::Database,
+        R: 'e + Send + Unpin + for<'r> FromRow<'r, DB::Row>,
+        E: 'e + Executor<'c, Database = DB>,
+        i64: for<'a> Encode<'a, DB> + Type<DB>,
+        for<'a> DB::Arguments<'a>: IntoArguments<'a, DB>,
+        'q: 'e,
+    {
+        if self.exhausted {
+            return Ok(None);
+        }
+
+        let query = sqlx::query_as(self.sql)
+            .bind(self.cursor)
+            .bind(i64::from(self.limit));
+
+        // The rows are counted before being put in the table, as it removes the duplicate rowids
+        let mut fetched: usize = 0;
+        let rows = check_rowids(query.fetch(executor)).inspect_ok(|_| fetched += 1);
+        let page: Table<R, S> = Table::try_from_stream(rows).await?;
+
+        self.exhausted = fetched < self.limit as usize;
+        match page.iter().map(HasRowID::rowid).max() {
+            Some(last) => self.cursor = last,
+            None => return Ok(None),
+        }
+
+        Ok(Some(page))
+    }
+
+    /// Fetch all the remaining pages as a stream. The executor must be reusable for each page, like a `&Pool`
+    pub fn into_stream<'e, 'c: 'e, DB, E>(
+        self,
+        executor: E,
+    ) -> impl Stream<Item = Result<Table<R, S>, sqlx::Error>> + 'e
+    where
+        DB: sqlx::Database,
+        R: 'e + Send + Unpin + for<'r> FromRow<'r, DB::Row>,
+        S: 'e,
+        E: 'e + Executor<'c, Database = DB> + Copy,
+        i64: for<'a> Encode<'a, DB> + Type<DB>,
+        for<'a> DB::Arguments<'a>: IntoArguments<'a, DB>,
+        'q: 'e,
+    {
+        stream::try_unfold(self, move |mut paginator| async move {
+            let page = paginator.next_page(executor).await?;
+            Ok(page.map(|page| (page, paginator)))
+        })
+    }
+
+    /// Fetch all the remaining rows as a stream, one page at a time. The executor must be reusable for each page, like a `&Pool`
+    ///
+    /// The rows are yielded in rowid order, whatever the storage of the pages
+    pub fn into_row_stream<'e, 'c: 'e, DB, E>(
+        self,
+        executor: E,
+    ) -> impl Stream<Item = Result<R, sqlx::Error>> + 'e
+    where
+        DB: sqlx::Database,
+        R: 'e + Send + Unpin + for<'r> FromRow<'r, DB::Row>,
+        S: 'e,
+        E: 'e + Executor<'c, Database = DB> + Copy,
+        i64: for<'a> Encode<'a, DB> + Type<DB>,
+        for<'a> DB::Arguments<'a>: IntoArguments<'a, DB>,
+        'q: 'e,
+    {
+        self.into_stream(executor)
+            .map_ok(|page| {
+                let mut rows = page.into_iter().collect::<Vec<_>>();
+                rows.sort_unstable_by_key(HasRowID::rowid);
+                stream::iter(rows).map(Ok)
+            })
+            .try_flatten()
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+    use std::collections::BTreeMap;
+
+    use futures::TryStreamExt as _;
+    use sqlx::FromRow;
+    use sqlx::SqlitePool;
+
+    use crate::has_rowid::HasRowID;
+    use crate::tables::paginate::Paginator;
+
+    const QUERY: &str = "SELECT id FROM listens WHERE id > ? ORDER BY id LIMIT ?";
+
+    #[derive(Debug, FromRow)]
+    struct Listen {
+        id: i64,
+    }
+
+    impl HasRowID for Listen {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[tokio::test]
+    async fn paginator_test() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql("CREATE TABLE listens (id INTEGER PRIMARY KEY); INSERT INTO listens VALUES (1), (2), (4), (8), (16);")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut paginator = Paginator::<Listen>::new(QUERY, 2);
+        let page = paginator.next_page(&pool).await.unwrap().unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(paginator.cursor(), 2);
+
+        let mut paginator =
+            Paginator::<Listen, BTreeMap<i64, Listen>>::resume(QUERY, 2, paginator.cursor());
+        let page = paginator.next_page(&pool).await.unwrap().unwrap();
+        assert_eq!(page.first().unwrap().id, 4);
+        assert_eq!(paginator.next_page(&pool).await.unwrap().unwrap().len(), 1);
+        assert!(paginator.is_exhausted());
+        assert!(paginator.next_page(&pool).await.unwrap().is_none());
+
+        let rows = Paginator::<Listen>::new(QUERY, 2)
+            .into_row_stream(&pool)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let ids = rows.iter().map(|row| row.id).collect::<Vec<_>>();
+        assert_eq!(ids, [1, 2, 4, 8, 16]);
+
+        // A full page with duplicate rowids isn't the last one
+        let duplicated = "SELECT listens.id FROM listens, (SELECT 1 UNION ALL SELECT 2) WHERE listens.id > ? ORDER BY listens.id LIMIT ?";
+        let mut paginator = Paginator::<Listen>::new(duplicated, 2);
+        assert_eq!(paginator.next_page(&pool).await.unwrap().unwrap().len(), 1);
+        assert!(!paginator.is_exhausted());
+        let rows = paginator
+            .into_row_stream(&pool)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 4);
+
+        let mut paginator = Paginator::<Listen>::new(QUERY, 0);
+        assert!(paginator.is_exhausted());
+        assert!(paginator.next_page(&pool).await.unwrap().is_none());
+
+        // The rows before the rowid 1 aren't skipped, but they can't be put in a table
+        sqlx::raw_sql("INSERT INTO listens VALUES (-1);")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut paginator = Paginator::<Listen>::new(QUERY, 2);
+        assert!(paginator.next_page(&pool).await.is_err());
+    }
+}
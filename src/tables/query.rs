@@ -0,0 +1,210 @@
+use core::hash::Hash;
+use std::collections::HashMap;
+
+use crate::Table;
+use crate::ZeroToManyJoin;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::HasKey;
+use crate::has_rowid::HasRowID;
+
+impl<R, S> Table<R, S>
+where
+    R: HasRowID,
+    S: RowStorage<R, Key = i64>,
+{
+    /// Only keep the rows for which `f` returns true
+    pub fn filter<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&R) -> bool,
+    {
+        self.retain(f);
+        self
+    }
+
+    /// Split the table in two. The first table has the rows for which `f` returns true, the second the others
+    pub fn partition<F>(self, mut f: F) -> (Self, Self)
+    where
+        F: FnMut(&R) -> bool,
+    {
+        let mut matching = Self::default();
+        let mut others = Self::default();
+
+        for row in self {
+            if f(&row) {
+                matching.insert(row);
+            } else {
+                others.insert(row);
+            }
+        }
+
+        (matching, others)
+    }
+
+    /// Group the rows by the key returned by `key`
+    pub fn group_by<K, F>(self, mut key: F) -> HashMap<K, Self>
+    where
+        K: Eq + Hash,
+        F: FnMut(&R) -> K,
+    {
+        let mut groups: HashMap<K, Self> = HashMap::new();
+
+        for row in self {
+            groups.entry(key(&row)).or_default().insert(row);
+        }
+
+        groups
+    }
+
+    /// Group the rows by a key that has a rowid, like the id of a parent row.
+    ///
+    /// The rows with an invalid key, like the rowid 0, are grouped under the `None` entry
+    ///
+    /// ```
+    /// # use sequelles::Table;
+    /// # use sequelles::has_rowid::HasRowID;
+    /// struct Listen {
+    ///     id: i64,
+    ///     recording_id: i64,
+    /// }
+    /// # impl HasRowID for Listen {
+    /// #     fn rowid(&self) -> i64 {
+    /// #         self.id
+    /// #     }
+    /// # }
+    ///
+    /// let listens: Table<Listen> = Table::from(vec![
+    ///     Listen { id: 1, recording_id: 10 },
+    ///     Listen { id: 2, recording_id: 10 },
+    ///     Listen { id: 3, recording_id: 20 },
+    /// ]);
+    ///
+    /// let by_recording = listens.group_by_rowid(|listen| listen.recording_id);
+    /// assert_eq!(by_recording.get_by_id(10).unwrap().len(), 2);
+    /// ```
+    pub fn group_by_rowid<K, F>(self, mut key: F) -> ZeroToManyJoin<K, R>
+    where
+        K: HasRowID,
+        F: FnMut(&R) -> K,
+    {
+        let mut join = ZeroToManyJoin::default();
+
+        for row in self {
+            let key = Some(key(&row)).filter(HasKey::has_valid_key);
+            join.push_entry(key, row);
+        }
+
+        join
+    }
+
+    /// Group the rows under the rows of another table, using `key` to get the rowid of the parent row.
+    ///
+    /// All the parent rows get an entry, even without children. The rows that don't have a parent are grouped under the `None` entry
+    pub fn group_into<L, T, F>(self, parents: T, mut key: F) -> ZeroToManyJoin<L, R>
+    where
+        L: HasRowID,
+        T: IntoIterator<Item = L>,
+        F: FnMut(&R) -> i64,
+    {
+        let mut join = ZeroToManyJoin::default();
+
+        for parent in parents {
+            join.insert_left(Some(parent));
+        }
+
+        for row in self {
+            let parent_id = key(&row);
+
+            if parent_id != 0 && join.contains_id(parent_id) {
+                join.push_right_by_id(parent_id, row);
+            } else {
+                join.push_entry(None, row);
+            }
+        }
+
+        join
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use crate::has_rowid::HasRowID;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Listen {
+        id: i64,
+        recording_id: i64,
+    }
+
+    impl HasRowID for Listen {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    fn listens() -> Table<Listen> {
+        Table::from(vec![
+            Listen {
+                id: 1,
+                recording_id: 10,
+            },
+            Listen {
+                id: 2,
+                recording_id: 10,
+            },
+            Listen {
+                id: 3,
+                recording_id: 10,
+            },
+            Listen {
+                id: 4,
+                recording_id: 20,
+            },
+            Listen {
+                id: 5,
+                recording_id: 0,
+            },
+        ])
+    }
+
+    #[test]
+    fn filter_partition_test() {
+        let even = listens().filter(|listen| listen.id % 2 == 0);
+        assert_eq!(even.len(), 2);
+        assert!(even.get(4).is_some());
+
+        let (tens, others) = listens().partition(|listen| listen.recording_id == 10);
+        assert_eq!(tens.len(), 3);
+        assert_eq!(others.len(), 2);
+
+        assert!(Table::<Listen>::new().filter(|_| true).is_empty());
+        let (matching, others) = Table::<Listen>::new().partition(|_| true);
+        assert!(matching.is_empty() && others.is_empty());
+    }
+
+    #[test]
+    fn group_test() {
+        let groups = listens().group_by(|listen| listen.recording_id);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[&10].len(), 3);
+        assert!(
+            Table::<Listen>::new()
+                .group_by(|listen| listen.id)
+                .is_empty()
+        );
+
+        let by_recording = listens().group_by_rowid(|listen| listen.recording_id);
+        assert_eq!(by_recording.get_by_id(10).unwrap().len(), 3);
+        assert_eq!(by_recording.without_left().unwrap().len(), 1);
+
+        // Recording 30 has no listens, and recordings 0 and 20 don't exist
+        let grouped = listens().group_into([10, 30], |listen| listen.recording_id);
+        assert_eq!(grouped.get_by_id(10).unwrap().len(), 3);
+        assert!(grouped.get_by_id(30).unwrap().is_empty());
+        assert_eq!(grouped.without_left().unwrap().len(), 2);
+        assert!(grouped.get_by_id(20).is_none());
+
+        let grouped = Table::<Listen>::new().group_into([10], |listen| listen.recording_id);
+        assert!(grouped.get_by_id(10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,381 @@
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::hash::Hasher;
+use core::ops::Deref;
+use std::collections::HashMap;
+use std::hash::DefaultHasher;
+use std::sync::PoisonError;
+use std::sync::RwLock;
+use std::sync::RwLockReadGuard;
+use std::sync::RwLockWriteGuard;
+use std::thread::available_parallelism;
+
+use snafu::Snafu;
+
+use crate::Table;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::ByKey;
+use crate::has_key::HasKey;
+use crate::has_key::IntoKey;
+use crate::has_key::assert_valid_key;
+use crate::has_rowid::HasRowID;
+use crate::tables::merge::Resolution;
+use crate::tables::merge::UpsertOutcome;
+
+/// A [`Table`] that can be shared between threads and tasks, usually behind an [`Arc`](std::sync::Arc).
+///
+/// The rows are split between multiple shards, each behind its own lock, so accesses to rows of different shards don't block each other.
+///
+/// The locks are blocking, so the guards returned by [`SharedTable::read`] and [`SharedTable::write`] shouldn't be held across an `.await`
+///
+/// It doesn't implement [`AsHashMap`](crate::datastructures::as_hashmap::AsHashMap), as the rows are split between several locked maps.
+/// Use [`SharedTable::into_table`] to get a single map back.
+pub struct SharedTable<R, S = HashMap<i64, R, BuildRowIdHasher>> {
+    shards: Box<[RwLock<Table<R, S>>]>,
+}
+
+impl<R> SharedTable<R>
+where
+    R: HasRowID,
+{
+    /// Create a new table, with a number of shards based on the available parallelism
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<R, S> SharedTable<R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    /// Create a new table with the given number of shards. There is always at least one shard
+    pub fn with_shards(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1))
+                .map(|_| RwLock::new(Table::default()))
+                .collect(),
+        }
+    }
+
+    fn shard(&self, key: &R::Key) -> &RwLock<Table<R, S>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+
+        // The shard count is small, so the cast can't truncate
+        &self.shards[(hasher.finish() % self.shards.len() as u64) as usize]
+    }
+
+    fn read_shard(&self, key: &R::Key) -> RwLockReadGuard<'_, Table<R, S>> {
+        // A panic can't leave a table in an invalid state, so poisoning is ignored
+        self.shard(key)
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_shard(&self, key: &R::Key) -> RwLockWriteGuard<'_, Table<R, S>> {
+        self.shard(key)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Insert a row, returning the row it replaced
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row doesn't have a valid key, like an unsaved row with the rowid 0
+    #[track_caller]
+    pub fn insert(&self, row: R) -> Option<R> {
+        assert_valid_key(&row);
+        match self.write_shard(&row.key()).upsert(row) {
+            UpsertOutcome::Conflict(Resolution::Replaced(old)) => Some(old),
+            _ => None,
+        }
+    }
+
+    /// Lock the row for reading. The other rows of its shard can still be read, but not modified
+    pub fn read<I>(&self, key: I) -> Option<SharedRowRef<'_, R, S>>
+    where
+        I: IntoKey<R>,
+    {
+        let key = key.into_key();
+        let guard = self.read_shard(&key);
+        guard.get(ByKey(key.clone()))?;
+
+        Some(SharedRowRef { guard, key })
+    }
+
+    /// Lock the row for writing. The other rows of its shard are locked until the guard is dropped
+    pub fn write<I>(&self, key: I) -> Option<SharedRowMut<'_, R, S>>
+    where
+        I: IntoKey<R>,
+    {
+        let key = key.into_key();
+        let guard = self.write_shard(&key);
+        guard.get(ByKey(key.clone()))?;
+
+        Some(SharedRowMut { guard, key })
+    }
+
+    /// Get a clone of a row
+    pub fn get_cloned<I>(&self, key: I) -> Option<R>
+    where
+        R: Clone,
+        I: IntoKey<R>,
+    {
+        let key = key.into_key();
+        self.read_shard(&key).get(ByKey(key.clone())).cloned()
+    }
+
+    /// Remove a row from the table
+    pub fn remove<I>(&self, key: I) -> Option<R>
+    where
+        I: IntoKey<R>,
+    {
+        let key = key.into_key();
+        self.write_shard(&key).remove(ByKey(key.clone()))
+    }
+
+    pub fn contains<I>(&self, key: I) -> bool
+    where
+        I: IntoKey<R>,
+    {
+        let key = key.into_key();
+        self.read_shard(&key).get(ByKey(key.clone())).is_some()
+    }
+
+    /// Only keep the rows for which `f` returns true. The shards are locked one at a time
+    pub fn retain<F>(&self, mut f: F)
+    where
+        F: FnMut(&R) -> bool,
+    {
+        for shard in &self.shards {
+            shard
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .retain(&mut f);
+        }
+    }
+
+    /// The number of rows. As the shards are counted one at a time, this may be outdated if other threads are writing
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap_or_else(PoisonError::into_inner).len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy the rows into a [`Table`]. The shards are locked one at a time
+    pub fn snapshot(&self) -> Table<R, S>
+    where
+        R: Clone,
+    {
+        let mut table = Table::default();
+
+        for shard in &self.shards {
+            let shard = shard.read().unwrap_or_else(PoisonError::into_inner);
+            table.extend(shard.iter().cloned());
+        }
+
+        table
+    }
+
+    /// Merge the shards back into a [`Table`]
+    pub fn into_table(self) -> Table<R, S> {
+        let mut table = Table::default();
+
+        for shard in self.shards {
+            table.extend(shard.into_inner().unwrap_or_else(PoisonError::into_inner));
+        }
+
+        table
+    }
+}
+
+impl<R, S> Default for SharedTable<R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    fn default() -> Self {
+        let threads = available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards((threads * 4).next_power_of_two())
+    }
+}
+
+impl<R, S> From<Table<R, S>> for SharedTable<R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    fn from(table: Table<R, S>) -> Self {
+        let shared = Self::default();
+
+        for row in table {
+            shared.insert(row);
+        }
+
+        shared
+    }
+}
+
+/// A read lock on a row of a [`SharedTable`]
+pub struct SharedRowRef<'t, R, S>
+where
+    R: HasKey,
+{
+    guard: RwLockReadGuard<'t, Table<R, S>>,
+    key: R::Key,
+}
+
+impl<R, S> Deref for SharedRowRef<'_, R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard
+            .get(ByKey(self.key.clone()))
+            .expect("The row should be kept while locked")
+    }
+}
+
+/// A write lock on a row of a [`SharedTable`]. Use [`SharedRowMut::update`] to modify it
+pub struct SharedRowMut<'t, R, S>
+where
+    R: HasKey,
+{
+    guard: RwLockWriteGuard<'t, Table<R, S>>,
+    key: R::Key,
+}
+
+impl<R, S> Deref for SharedRowMut<'_, R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard
+            .get(ByKey(self.key.clone()))
+            .expect("The row should be kept while locked")
+    }
+}
+
+impl<R, S> SharedRowMut<'_, R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
+    /// Modify the row, returning the result of `f`.
+    ///
+    /// Returns an error if `f` changed the key, as the row would be out of place in its shard. The row is then restored to its previous value
+    pub fn update<F, U>(&mut self, f: F) -> Result<U, KeyChangedError<R::Key>>
+    where
+        F: FnOnce(&mut R) -> U,
+        R: Clone,
+        R::Key: Debug,
+    {
+        let row = self
+            .guard
+            .get_mut(ByKey(self.key.clone()))
+            .expect("The row should be kept while locked");
+        let old = row.clone();
+        let result = f(row);
+
+        let new_key = row.key();
+        if new_key != self.key {
+            *row = old;
+            return KeyChangedSnafu {
+                key: self.key.clone(),
+                new_key,
+            }
+            .fail();
+        }
+
+        Ok(result)
+    }
+}
+
+/// The error of [`SharedRowMut::update`], when the key of the row was changed
+#[derive(Debug, Snafu, Clone, PartialEq, Eq)]
+#[snafu(display("The key {key:?} of a shared row can't be changed to {new_key:?}"))]
+pub struct KeyChangedError<K>
+where
+    K: Debug,
+{
+    pub key: K,
+    pub new_key: K,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::SharedTable;
+    use crate::has_rowid::HasRowID;
+    use crate::tables::shared_table::KeyChangedError;
+
+    #[derive(Clone)]
+    struct Counter {
+        id: i64,
+        count: u32,
+    }
+
+    impl HasRowID for Counter {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[test]
+    fn shared_table_test() {
+        let table: SharedTable<Counter> = SharedTable::with_shards(4);
+        for id in 1..=10 {
+            table.insert(Counter { id, count: 0 });
+        }
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for id in 1..=10 {
+                        table
+                            .write(id)
+                            .unwrap()
+                            .update(|row| row.count += 1)
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(table.len(), 10);
+        assert!(table.read(11).is_none());
+        assert!((1..=10).all(|id| table.read(id).unwrap().count == 8));
+
+        let mut row = table.write(1).unwrap();
+        assert_eq!(
+            row.update(|row| {
+                row.id = 42;
+                row.count = 0;
+            }),
+            Err(KeyChangedError {
+                key: 1,
+                new_key: 42
+            })
+        );
+        assert_eq!((row.id, row.count), (1, 8));
+        drop(row);
+        assert!(table.read(42).is_none());
+
+        table.remove(3);
+        let table = table.into_table();
+        assert_eq!(table.len(), 9);
+    }
+}
@@ -0,0 +1,286 @@
+use core::hash::BuildHasher;
+
+use snafu::Snafu;
+use snafu::ensure;
+
+use crate::ManyToManyJoin;
+use crate::ManyToZeroJoin;
+use crate::RowIDMap;
+use crate::Table;
+use crate::ZeroToManyJoin;
+use crate::datastructures::key_set::KeySet;
+use crate::datastructures::storage::RowStorage;
+use crate::has_rowid::HasRowID;
+
+/// Check that a row can be inserted in place of `existing`.
+///
+/// With the `strict-panic` feature, debug builds panic on invalid rows instead of returning the error
+#[track_caller]
+fn check_insert<R>(row: &R, existing: Option<&R>) -> Result<(), InsertError>
+where
+    R: HasRowID + PartialEq,
+{
+    strict(check_row(row, existing))
+}
+
+/// With the `strict-panic` feature, debug builds panic on errors
+#[track_caller]
+fn strict(result: Result<(), InsertError>) -> Result<(), InsertError> {
+    #[cfg(all(feature = "strict-panic", debug_assertions))]
+    if let Err(err) = &result {
+        panic!("{err}");
+    }
+
+    result
+}
+
+fn check_row<R>(row: &R, existing: Option<&R>) -> Result<(), InsertError>
+where
+    R: HasRowID + PartialEq,
+{
+    let rowid = row.rowid();
+    ensure!(rowid > 0, InvalidRowidSnafu { rowid });
+    ensure!(
+        existing.is_none_or(|existing| existing == row),
+        DuplicateRowidSnafu { rowid }
+    );
+
+    Ok(())
+}
+
+/// Check that the right elements of a join entry don't replace different ones
+fn check_rights<V>(rowid: i64, value: &V, existing: Option<&V>) -> Result<(), InsertError>
+where
+    V: PartialEq,
+{
+    ensure!(
+        existing.is_none_or(|existing| existing == value),
+        OccupiedEntrySnafu { rowid }
+    );
+
+    Ok(())
+}
+
+impl<R, S> Table<R, S>
+where
+    R: HasRowID + PartialEq,
+    S: RowStorage<R, Key = i64>,
+{
+    /// Insert a row, checking that its rowid is above 0, and that it doesn't replace a different row.
+    ///
+    /// Inserting a row equal to the existing one is allowed
+    #[track_caller]
+    pub fn try_insert(&mut self, row: R) -> Result<(), InsertError> {
+        check_insert(&row, self.get(row.rowid()))?;
+        self.insert(row);
+
+        Ok(())
+    }
+
+    /// Insert all the rows with [`Table::try_insert`], stopping at the first invalid row
+    #[track_caller]
+    pub fn try_extend<I>(&mut self, rows: I) -> Result<(), InsertError>
+    where
+        I: IntoIterator<Item = R>,
+    {
+        rows.into_iter().try_for_each(|row| self.try_insert(row))
+    }
+}
+
+impl<K, V, S> RowIDMap<K, V, S>
+where
+    K: HasRowID + PartialEq,
+    S: RowStorage<(K, V), Key = i64>,
+{
+    /// Insert a key-value pair, checking that the rowid of the key is above 0, and that it doesn't replace a different key.
+    ///
+    /// Returns the previous value of the key
+    #[track_caller]
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, InsertError> {
+        let existing = self.get_key_value_by_id(key.rowid()).map(|(key, _)| key);
+        check_insert(&key, existing)?;
+
+        Ok(self.insert(key, value).map(|(_, val)| val))
+    }
+}
+
+impl<L, R, S> ManyToZeroJoin<L, R, S>
+where
+    L: HasRowID + PartialEq,
+    R: PartialEq,
+    S: RowStorage<(L, Option<R>), Key = i64>,
+{
+    /// Insert a key-value pair, checking the left element like [`RowIDMap::try_insert`].
+    ///
+    /// An existing entry is never replaced, unless it is equal to the new one. Use [`ManyToZeroJoin::insert`] to replace it
+    #[track_caller]
+    pub fn try_insert(&mut self, left: L, right: Option<R>) -> Result<(), InsertError> {
+        let existing = self.get_key_value_by_id(left.rowid());
+        strict(
+            check_row(&left, existing.map(|(key, _)| key))
+                .and_then(|()| check_rights(left.rowid(), &right, existing.map(|(_, val)| val))),
+        )?;
+        self.insert(left, right);
+
+        Ok(())
+    }
+}
+
+impl<L, R, S> ZeroToManyJoin<L, R, S>
+where
+    L: HasRowID + PartialEq,
+    R: PartialEq,
+    S: RowStorage<(L, Vec<R>), Key = i64>,
+{
+    /// Insert an entry, checking the left element like [`RowIDMap::try_insert`]. The `None` left element is always valid.
+    ///
+    /// An existing entry is never replaced, unless it is equal to the new one. Use [`ZeroToManyJoin::insert`] to replace it,
+    /// or [`ZeroToManyJoin::push_entries`] to add to its right elements
+    #[track_caller]
+    pub fn try_insert(&mut self, key: Option<L>, value: Vec<R>) -> Result<(), InsertError> {
+        let checked = match &key {
+            Some(left) => {
+                let existing = self.get_key_value_by_id(left.rowid());
+                check_row(left, existing.map(|(key, _)| key))
+                    .and_then(|()| check_rights(left.rowid(), &value, existing.map(|(_, val)| val)))
+            }
+            None => check_rights(key.rowid(), &value, self.without_left()),
+        };
+        strict(checked)?;
+
+        self.insert(key, value);
+
+        Ok(())
+    }
+}
+
+impl<L, R, H, LS, RS> ManyToManyJoin<L, R, H, LS, RS>
+where
+    L: HasRowID + PartialEq,
+    R: HasRowID + PartialEq,
+    H: BuildHasher + Default,
+    LS: KeySet<i64>,
+    RS: KeySet<i64>,
+{
+    /// Add a new element to the left table, checking it like [`Table::try_insert`]
+    #[track_caller]
+    pub fn try_add_left(&mut self, left: L) -> Result<(), InsertError> {
+        check_insert(&left, self.get_left(left.rowid()))?;
+        self.add_left(left);
+
+        Ok(())
+    }
+
+    /// Add a new element to the right table, checking it like [`Table::try_insert`]
+    #[track_caller]
+    pub fn try_add_right(&mut self, right: R) -> Result<(), InsertError> {
+        check_insert(&right, self.get_right(right.rowid()))?;
+        self.add_right(right);
+
+        Ok(())
+    }
+}
+
+/// An error returned by the checked insertions, like [`Table::try_insert`]
+#[derive(Debug, Snafu, Clone, PartialEq, Eq)]
+pub enum InsertError {
+    #[snafu(display("The rowid {rowid} is invalid, as rowids are above 0"))]
+    InvalidRowidError { rowid: i64 },
+
+    #[snafu(display("The rowid {rowid} is already used by a different row"))]
+    DuplicateRowidError { rowid: i64 },
+
+    #[snafu(display("The join entry {rowid} already has different right elements"))]
+    OccupiedEntryError { rowid: i64 },
+}
+
+#[cfg(all(test, not(all(feature = "strict-panic", debug_assertions))))]
+mod test {
+    use crate::ManyToZeroJoin;
+    use crate::RowIDMap;
+    use crate::Table;
+    use crate::ZeroToManyJoin;
+    use crate::has_rowid::HasRowID;
+    use crate::tables::strict::InsertError;
+
+    #[derive(PartialEq)]
+    struct Recording {
+        id: i64,
+        title: &'static str,
+    }
+
+    impl HasRowID for Recording {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    #[test]
+    fn try_insert_test() {
+        let mut table: Table<i64> = Table::new();
+        assert_eq!(table.try_extend([1, 2, 2]), Ok(()));
+        assert_eq!(
+            table.try_insert(0),
+            Err(InsertError::InvalidRowidError { rowid: 0 })
+        );
+        assert_eq!(table.len(), 2);
+
+        let mut map: RowIDMap<Recording, u32> = RowIDMap::default();
+        map.try_insert(Recording { id: 1, title: "a" }, 1).unwrap();
+        assert_eq!(
+            map.try_insert(Recording { id: 1, title: "b" }, 2),
+            Err(InsertError::DuplicateRowidError { rowid: 1 })
+        );
+        assert_eq!(
+            map.try_insert(Recording { id: 1, title: "a" }, 3),
+            Ok(Some(1))
+        );
+    }
+
+    #[test]
+    fn join_try_insert_test() {
+        let mut join: ManyToZeroJoin<i64, &str> = ManyToZeroJoin::default();
+        join.try_insert(1, Some("a")).unwrap();
+        assert_eq!(join.try_insert(1, Some("a")), Ok(()));
+        assert_eq!(
+            join.try_insert(1, None),
+            Err(InsertError::OccupiedEntryError { rowid: 1 })
+        );
+        assert_eq!(join.get_by_id(1), Some(&Some("a")));
+
+        let mut join: ZeroToManyJoin<i64, &str> = ZeroToManyJoin::default();
+        join.try_insert(Some(1), vec!["a"]).unwrap();
+        join.try_insert(None, vec!["b"]).unwrap();
+        assert_eq!(
+            join.try_insert(Some(1), vec!["c"]),
+            Err(InsertError::OccupiedEntryError { rowid: 1 })
+        );
+        assert_eq!(
+            join.try_insert(None, Vec::new()),
+            Err(InsertError::OccupiedEntryError { rowid: 0 })
+        );
+        assert_eq!(join.get_by_id(1), Some(&vec!["a"]));
+        assert_eq!(join.without_left(), Some(&vec!["b"]));
+    }
+}
+
+#[cfg(all(test, feature = "strict-panic", debug_assertions))]
+mod strict_panic_test {
+    use crate::Table;
+    use crate::ZeroToManyJoin;
+
+    #[test]
+    #[should_panic(expected = "The rowid 0 is invalid")]
+    fn strict_panic_test() {
+        let mut table: Table<i64> = Table::new();
+        let _ = table.try_insert(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The join entry 1 already has different right elements")]
+    fn strict_panic_join_test() {
+        let mut join: ZeroToManyJoin<i64, &str> = ZeroToManyJoin::default();
+        join.insert(Some(1), vec!["a"]);
+        let _ = join.try_insert(Some(1), vec!["b"]);
+    }
+}
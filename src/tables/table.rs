@@ -1,10 +1,34 @@
+use core::marker::PhantomData;
+use core::ops::Bound;
+use core::ops::RangeBounds;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::collections::hash_map::IntoValues;
 
+use crate::datastructures::as_hashmap::AsHashMap;
+use crate::datastructures::as_hashmap::AsHashMapMut;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::key_set::KeySet;
+use crate::datastructures::storage::OrderedRowStorage;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::HasKey;
+use crate::has_key::IntoKey;
+use crate::has_key::assert_valid_key;
 use crate::has_rowid::HasRowID;
 
-/// This represent a database table. All the rows are indexed by rowid.
-pub struct Table<R>(HashMap<i64, R>);
+/// This represent a database table. All the rows are indexed by rowid, or by their [`HasKey::Key`].
+///
+/// The rows are kept in a [`RowStorage`], which is an [`HashMap`] using a [`BuildRowIdHasher`] by default.
+///
+/// Only rows with a valid key can be inserted, so rows with a rowid must have a rowid above 0. Inserting an unsaved row panics.
+/// Use the checked insertions like [`Table::try_insert`] to get an error instead,
+/// or `Table<Persisted<R>>` with [`Persisted`](crate::Persisted) to check the rows when they are created.
+pub struct Table<R, S = HashMap<i64, R, BuildRowIdHasher>>(S, PhantomData<R>);
+
+/// A [`Table`] that iterates over its rows in rowid order
+pub type OrderedTable<R> = Table<R, BTreeMap<i64, R>>;
+
+/// A [`Table`] of rows indexed by a key other than a rowid. For rowids, this is the same type as [`Table`]
+pub type KeyedTable<R, H = BuildRowIdHasher> = Table<R, HashMap<<R as HasKey>::Key, R, H>>;
 
 impl<R> Table<R>
 where
@@ -12,31 +36,76 @@ where
 {
     /// Create a new table
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
     }
+}
 
+impl<R, S> Table<R, S>
+where
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
+{
     /// Insert a new row in the table
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row doesn't have a valid key, like an unsaved row with the rowid 0
+    #[track_caller]
     pub fn insert(&mut self, value: R) {
-        self.0.insert(value.rowid(), value);
+        assert_valid_key(&value);
+        self.0.insert(value.key(), value);
     }
 
-    /// Get a row by its rowid
-    pub fn get(&self, key: &i64) -> Option<&R> {
-        self.0.get(key)
+    /// Get a row by its key
+    pub fn get<I>(&self, key: I) -> Option<&R>
+    where
+        I: IntoKey<R>,
+    {
+        self.0.get(&key.into_key())
     }
 
-    /// Get a mutable reference to a row by its rowid
-    pub fn get_mut(&mut self, key: &i64) -> Option<&mut R> {
-        self.0.get_mut(key)
+    /// Get a mutable reference to a row by its key
+    pub fn get_mut<I>(&mut self, key: I) -> Option<&mut R>
+    where
+        I: IntoKey<R>,
+    {
+        self.0.get_mut(&key.into_key())
     }
 
     /// Remove a value from the table
-    pub fn remove(&mut self, key: &i64) -> Option<R> {
-        self.0.remove(key)
+    pub fn remove<I>(&mut self, key: I) -> Option<R>
+    where
+        I: IntoKey<R>,
+    {
+        self.0.remove(&key.into_key())
+    }
+
+    /// The keys of all the rows, in any [`KeySet`]. For rowids, this is usually a [`RowIdSet`](crate::RowIdSet)
+    pub fn key_set<KS>(&self) -> KS
+    where
+        KS: KeySet<R::Key>,
+    {
+        self.0.iter().map(|row| row.key()).collect()
+    }
+
+    /// The keys of the set that aren't in the table, like the rows that still need to be fetched
+    pub fn missing_keys<KS>(&self, keys: &KS) -> KS
+    where
+        KS: KeySet<R::Key>,
+    {
+        keys.iter().filter(|key| !self.0.contains(key)).collect()
     }
 
-    pub fn iter(&self) -> std::collections::hash_map::Values<'_, i64, R> {
-        self.0.values()
+    /// Only keep the rows for which `f` returns true
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&R) -> bool,
+    {
+        self.0.retain(|row| f(row));
+    }
+
+    pub fn iter(&self) -> S::Iter<'_> {
+        self.0.iter()
     }
 
     pub fn len(&self) -> usize {
@@ -48,18 +117,73 @@ where
     }
 }
 
-impl<R> Default for Table<R> {
+impl<R, S> Table<R, S>
+where
+    R: HasKey,
+    S: OrderedRowStorage<R, Key = R::Key>,
+{
+    /// Iterate over the rows with a key in the given range, in key order
+    pub fn range<B>(&self, range: B) -> S::Range<'_>
+    where
+        B: RangeBounds<R::Key>,
+    {
+        self.0.range(range)
+    }
+
+    /// Iterate over the rows with a key strictly greater than `rowid`, in key order
+    pub fn after<I>(&self, rowid: I) -> S::Range<'_>
+    where
+        I: IntoKey<R>,
+    {
+        self.0
+            .range((Bound::Excluded(rowid.into_key()), Bound::Unbounded))
+    }
+
+    /// The row with the smallest key
+    pub fn first(&self) -> Option<&R> {
+        self.0.first()
+    }
+
+    /// The row with the greatest key
+    pub fn last(&self) -> Option<&R> {
+        self.0.last()
+    }
+}
+
+impl<R, H> AsHashMap<R::Key, R, H> for Table<R, HashMap<R::Key, R, H>>
+where
+    R: HasKey,
+{
+    fn as_hashmap(&self) -> &HashMap<R::Key, R, H> {
+        &self.0
+    }
+}
+
+impl<R, H> AsHashMapMut<R::Key, R, H> for Table<R, HashMap<R::Key, R, H>>
+where
+    R: HasKey,
+{
+    fn as_hashmap_mut(&mut self) -> &mut HashMap<R::Key, R, H> {
+        &mut self.0
+    }
+}
+
+impl<R, S> Default for Table<R, S>
+where
+    S: Default,
+{
     fn default() -> Self {
-        Self(HashMap::default())
+        Self(S::default(), PhantomData)
     }
 }
 
-impl<R> From<Vec<R>> for Table<R>
+impl<R, S> From<Vec<R>> for Table<R, S>
 where
-    R: HasRowID,
+    R: HasKey,
+    S: RowStorage<R, Key = R::Key>,
 {
     fn from(value: Vec<R>) -> Self {
-        let mut table = Self::new();
+        let mut table = Self::default();
         for item in value {
             table.insert(item);
         }
@@ -67,10 +191,116 @@ where
     }
 }
 
-impl<R> IntoIterator for Table<R> {
+impl<R, S> IntoIterator for Table<R, S>
+where
+    S: RowStorage<R>,
+{
     type Item = R;
-    type IntoIter = IntoValues<i64, R>;
+    type IntoIter = S::IntoIter;
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_values()
     }
 }
+
+/// Serialized as a sequence of rows
+#[cfg(feature = "serde")]
+impl<R, S> serde::Serialize for Table<R, S>
+where
+    R: serde::Serialize,
+    S: RowStorage<R>,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, R, S> serde::Deserialize<'de> for Table<R, S>
+where
+    R: HasKey + serde::Deserialize<'de>,
+    S: RowStorage<R, Key = R::Key>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct TableVisitor<R, S>(PhantomData<(R, S)>);
+
+        impl<'de, R, S> serde::de::Visitor<'de> for TableVisitor<R, S>
+        where
+            R: HasKey + serde::Deserialize<'de>,
+            S: RowStorage<R, Key = R::Key>,
+        {
+            type Value = Table<R, S>;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a sequence of rows")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut table = Table::default();
+
+                while let Some(row) = seq.next_element::<R>()? {
+                    if !row.has_valid_key() {
+                        return Err(serde::de::Error::custom("a row doesn't have a valid key"));
+                    }
+
+                    table.insert(row);
+                }
+
+                Ok(table)
+            }
+        }
+
+        deserializer.deserialize_seq(TableVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::ops::Bound;
+
+    use crate::RowIdSet;
+    use crate::Table;
+    use crate::datastructures::storage::dense::DenseStorage;
+    use crate::tables::table::OrderedTable;
+
+    #[test]
+    fn ordered_table_test() {
+        let table = OrderedTable::from(vec![5, 3, 8, 1, 4]);
+
+        assert_eq!(table.iter().copied().collect::<Vec<i64>>(), [1, 3, 4, 5, 8]);
+        assert_eq!(table.range(3..5).copied().collect::<Vec<i64>>(), [3, 4]);
+
+        // Inverted ranges are empty, like with the other storages
+        let (start, end) = (5, 3);
+        let dense: Table<i64, DenseStorage<i64>> = Table::from(vec![5, 3, 8, 1, 4]);
+        assert_eq!(table.range(start..end).count(), 0);
+        assert_eq!(dense.range(start..end).count(), 0);
+        assert_eq!(table.range(start..=end).count(), 0);
+        assert_eq!(table.range(4..4).count(), 0);
+        let excluded = (Bound::Excluded(4), Bound::Excluded(4));
+        assert_eq!(table.range(excluded).count(), 0);
+        assert_eq!(dense.range(excluded).count(), 0);
+        assert_eq!(table.after(4).copied().collect::<Vec<i64>>(), [5, 8]);
+        assert_eq!(table.first(), Some(&1));
+        assert_eq!(table.last(), Some(&8));
+
+        let wanted: RowIdSet = [1, 2, 3].into_iter().collect();
+        assert_eq!(table.key_set::<RowIdSet>().len(), 5);
+        assert_eq!(table.missing_keys(&wanted).iter().collect::<Vec<_>>(), [2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rows without a valid key can't be stored")]
+    fn unsaved_row_test() {
+        let mut table: Table<i64> = Table::new();
+        table.insert(0);
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::Table;
+use crate::datastructures::as_hashmap::AsHashMap;
+use crate::datastructures::hasher::BuildRowIdHasher;
+use crate::datastructures::storage::RowStorage;
+use crate::has_key::IntoKey;
+use crate::has_rowid::HasRowID;
+
+/// A [`Table`] that records the changes made to it, so they can be written back to the database.
+///
+/// Rows with a rowid of `0` are considered new rows, that don't have a rowid yet. Every other row is expected to exist in the database,
+/// unless it was inserted in the tracked table.
+///
+/// # Exemple
+/// ```
+/// # use sequelles::Table;
+/// # use sequelles::TrackedTable;
+/// # use sequelles::has_rowid::HasRowID;
+/// struct User {
+///     id: i64,
+///     name: String,
+/// }
+/// # impl HasRowID for User {
+/// #     fn rowid(&self) -> i64 {
+/// #         self.id
+/// #     }
+/// # }
+///
+/// let users: Table<User> = Table::from(vec![User { id: 1, name: "Alice".to_string() }]);
+/// let mut users = TrackedTable::from(users);
+///
+/// users.get_mut(&1).unwrap().name = "Alicia".to_string();
+/// users.insert(User { id: 0, name: "Bob".to_string() });
+///
+/// let changes = users.changeset();
+/// assert_eq!(changes.inserted.len(), 1);
+/// assert_eq!(changes.updated[0].name, "Alicia");
+/// ```
+pub struct TrackedTable<R, S = HashMap<i64, R, BuildRowIdHasher>> {
+    table: Table<R, S>,
+
+    /// The rows without rowid
+    new_rows: Vec<R>,
+
+    /// The rows inserted with a rowid that wasn't in the table
+    inserted: HashSet<i64>,
+    modified: HashSet<i64>,
+    removed: HashSet<i64>,
+}
+
+impl<R> TrackedTable<R>
+where
+    R: HasRowID,
+{
+    /// Create a new empty tracked table
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<R, S> TrackedTable<R, S>
+where
+    R: HasRowID,
+    S: RowStorage<R, Key = i64>,
+{
+    /// Insert a row. If a row with the same rowid exists, it is replaced and marked as modified.
+    ///
+    /// Unsaved rows, with a rowid of 0 or less, are added to the new rows
+    pub fn insert(&mut self, row: R) {
+        let rowid = row.rowid();
+
+        if rowid <= 0 {
+            self.new_rows.push(row);
+            return;
+        }
+
+        let existed = self.table.get(rowid).is_some() || self.removed.remove(&rowid);
+        self.table.insert(row);
+
+        if !existed {
+            self.inserted.insert(rowid);
+        } else if !self.inserted.contains(&rowid) {
+            self.modified.insert(rowid);
+        }
+    }
+
+    /// Get a row by its rowid
+    pub fn get<I>(&self, key: I) -> Option<&R>
+    where
+        I: IntoKey<R>,
+    {
+        self.table.get(key)
+    }
+
+    /// Get a mutable reference to a row by its rowid. The row is marked as modified
+    pub fn get_mut<I>(&mut self, key: I) -> Option<&mut R>
+    where
+        I: IntoKey<R>,
+    {
+        let key = key.into_key();
+        let row = self.table.get_mut(key)?;
+
+        if !self.inserted.contains(&key) {
+            self.modified.insert(key);
+        }
+
+        Some(row)
+    }
+
+    /// Remove a row from the table. The row is marked as removed if it exists in the database
+    pub fn remove<I>(&mut self, key: I) -> Option<R>
+    where
+        I: IntoKey<R>,
+    {
+        let key = key.into_key();
+        let row = self.table.remove(key)?;
+
+        self.modified.remove(&key);
+        if !self.inserted.remove(&key) {
+            self.removed.insert(key);
+        }
+
+        Some(row)
+    }
+
+    /// Iterate over the rows with a rowid
+    pub fn iter(&self) -> S::Iter<'_> {
+        self.table.iter()
+    }
+
+    /// The rows that don't have a rowid yet
+    pub fn new_rows(&self) -> &[R] {
+        &self.new_rows
+    }
+
+    /// The rows that don't have a rowid yet, to modify or remove them.
+    ///
+    /// They don't need to be tracked, as they will all be inserted
+    pub fn new_rows_mut(&mut self) -> &mut Vec<R> {
+        &mut self.new_rows
+    }
+
+    /// The number of rows, new rows included
+    pub fn len(&self) -> usize {
+        self.table.len() + self.new_rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return true if there are changes to write back
+    pub fn is_dirty(&self) -> bool {
+        !self.new_rows.is_empty()
+            || !self.inserted.is_empty()
+            || !self.modified.is_empty()
+            || !self.removed.is_empty()
+    }
+
+    /// Get the changes made since the table was created or last marked as clean.
+    ///
+    /// The changeset doesn't touch the database. Apply it with your own queries, ideally inside a single transaction.
+    pub fn changeset(&self) -> Changeset<'_, R> {
+        let mut inserted = self
+            .inserted
+            .iter()
+            .filter_map(|id| self.table.get(id))
+            .collect::<Vec<_>>();
+        inserted.sort_unstable_by_key(|row| row.rowid());
+        inserted.extend(self.new_rows.iter());
+
+        let mut updated = self
+            .modified
+            .iter()
+            .filter_map(|id| self.table.get(id))
+            .collect::<Vec<_>>();
+        updated.sort_unstable_by_key(|row| row.rowid());
+
+        let mut deleted = self.removed.iter().copied().collect::<Vec<_>>();
+        deleted.sort_unstable();
+
+        Changeset {
+            inserted,
+            updated,
+            deleted,
+        }
+    }
+
+    /// Forget the tracked changes, once they have been written back.
+    ///
+    /// The new rows are removed and returned, as their rowid is now known by the database.
+    /// Insert them again with their new rowid to keep them in the table.
+    pub fn mark_clean(&mut self) -> Vec<R> {
+        self.inserted.clear();
+        self.modified.clear();
+        self.removed.clear();
+        core::mem::take(&mut self.new_rows)
+    }
+
+    /// Stop tracking, and return the underlying table along with the new rows, as they can't be in the table without a rowid
+    pub fn into_table(self) -> (Table<R, S>, Vec<R>) {
+        (self.table, self.new_rows)
+    }
+}
+
+/// The rows with a rowid. There is no mutable access, as the changes wouldn't be tracked
+impl<R, H> AsHashMap<i64, R, H> for TrackedTable<R, HashMap<i64, R, H>>
+where
+    R: HasRowID,
+{
+    fn as_hashmap(&self) -> &HashMap<i64, R, H> {
+        self.table.as_hashmap()
+    }
+}
+
+impl<R, S> Default for TrackedTable<R, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::from(Table::default())
+    }
+}
+
+/// The rows of the table are considered to be already in the database
+impl<R, S> From<Table<R, S>> for TrackedTable<R, S> {
+    fn from(table: Table<R, S>) -> Self {
+        Self {
+            table,
+            new_rows: Vec::new(),
+            inserted: HashSet::new(),
+            modified: HashSet::new(),
+            removed: HashSet::new(),
+        }
+    }
+}
+
+/// The changes made to a [`TrackedTable`]. Created by [`TrackedTable::changeset`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Changeset<'t, R> {
+    /// The rows to insert. Rows with a rowid come first, sorted by rowid, then the new rows in insertion order
+    pub inserted: Vec<&'t R>,
+
+    /// The rows to update, sorted by rowid
+    pub updated: Vec<&'t R>,
+
+    /// The rowids of the rows to delete, sorted
+    pub deleted: Vec<i64>,
+}
+
+impl<R> Changeset<'_, R> {
+    /// Return true if there is nothing to write back
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Table;
+    use crate::TrackedTable;
+    use crate::has_rowid::HasRowID;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: i64,
+        name: &'static str,
+    }
+
+    impl HasRowID for User {
+        fn rowid(&self) -> i64 {
+            self.id
+        }
+    }
+
+    fn users() -> TrackedTable<User> {
+        TrackedTable::from(Table::from(vec![
+            User {
+                id: 1,
+                name: "alice",
+            },
+            User { id: 2, name: "bob" },
+        ]))
+    }
+
+    #[test]
+    fn tracked_table_test() {
+        let mut users = users();
+        assert!(!users.is_dirty());
+
+        users.insert(User {
+            id: 3,
+            name: "carol",
+        });
+        users.insert(User {
+            id: 1,
+            name: "alicia",
+        });
+        users.get_mut(3).unwrap().name = "caroline";
+        users.remove(2);
+        users.insert(User {
+            id: 0,
+            name: "dave",
+        });
+        users.insert(User { id: 0, name: "eve" });
+        users.new_rows_mut().retain(|user| user.name != "eve");
+
+        let changes = users.changeset();
+        assert_eq!(
+            changes.inserted,
+            [
+                &User {
+                    id: 3,
+                    name: "caroline"
+                },
+                &User {
+                    id: 0,
+                    name: "dave"
+                }
+            ]
+        );
+        assert_eq!(
+            changes.updated,
+            [&User {
+                id: 1,
+                name: "alicia"
+            }]
+        );
+        assert_eq!(changes.deleted, [2]);
+
+        // Removing an inserted row cancels its insertion, and a removed row can come back as an update
+        users.remove(3);
+        users.insert(User {
+            id: 2,
+            name: "bobby",
+        });
+        let changes = users.changeset();
+        assert_eq!(changes.inserted.len(), 1);
+        assert_eq!(changes.updated.len(), 2);
+        assert!(changes.deleted.is_empty());
+
+        let (table, new_rows) = users.into_table();
+        assert_eq!(table.len(), 2);
+        assert_eq!(
+            new_rows,
+            [User {
+                id: 0,
+                name: "dave"
+            }]
+        );
+    }
+
+    #[test]
+    fn mark_clean_test() {
+        let mut users = users();
+        users.remove(1);
+        users.insert(User {
+            id: 0,
+            name: "dave",
+        });
+
+        assert_eq!(
+            users.mark_clean(),
+            [User {
+                id: 0,
+                name: "dave"
+            }]
+        );
+        assert!(!users.is_dirty());
+        assert!(users.changeset().is_empty());
+    }
+}
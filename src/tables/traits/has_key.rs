@@ -0,0 +1,248 @@
+use core::hash::Hash;
+
+use crate::has_rowid::HasRowID;
+
+/// Trait for all row structs that can be indexed by a key. This is what [`Table`](crate::Table),
+/// [`RowIDMap`](crate::RowIDMap) and the joins use to index their rows.
+///
+/// Every [`HasRowID`] row has an [`i64`] key. Implement it directly for tables without rowids,
+/// like `WITHOUT ROWID` tables keyed by a text or a composite primary key.
+///
+/// # Exemple
+/// ```
+/// # use sequelles::KeyedTable;
+/// # use sequelles::has_key::HasKey;
+/// struct ListenCount {
+///     user_id: i64,
+///     recording_id: i64,
+///     count: u64,
+/// }
+///
+/// impl HasKey for ListenCount {
+///     type Key = (i64, i64);
+///
+///     fn key(&self) -> Self::Key {
+///         (self.user_id, self.recording_id)
+///     }
+/// }
+///
+/// let mut counts: KeyedTable<ListenCount> = KeyedTable::default();
+/// counts.insert(ListenCount { user_id: 1, recording_id: 2, count: 3 });
+///
+/// assert_eq!(counts.get((1, 2)).unwrap().count, 3);
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't have a key",
+    note = "implement `HasRowID` for rows with a rowid, or `HasKey` for other keys"
+)]
+pub trait HasKey {
+    type Key: Eq + Hash + Ord + Clone;
+
+    fn key(&self) -> Self::Key;
+
+    /// Whether the row can be stored under its key. All keys are valid by default
+    fn has_valid_key(&self) -> bool {
+        true
+    }
+}
+
+/// Rows with a rowid only have a valid key once saved, with a rowid above 0
+impl<T> HasKey for T
+where
+    T: HasRowID,
+{
+    type Key = i64;
+
+    fn key(&self) -> Self::Key {
+        self.rowid()
+    }
+
+    fn has_valid_key(&self) -> bool {
+        self.rowid() > 0
+    }
+}
+
+/// Check that a row can be stored in a table, a map or a join
+#[track_caller]
+pub(crate) fn assert_valid_key<T>(row: &T)
+where
+    T: HasKey,
+{
+    assert!(
+        row.has_valid_key(),
+        "Rows without a valid key can't be stored, like unsaved rows with the rowid 0"
+    );
+}
+
+/// A value that can be used as the key of a row of type `T`.
+///
+/// It is implemented for the common key types, and their references. Other key types can be wrapped in [`ByKey`]
+pub trait IntoKey<T>
+where
+    T: HasKey,
+{
+    fn into_key(self) -> T::Key;
+}
+
+/// Use any value as a key, for key types that don't implement [`IntoKey`] themselves
+pub struct ByKey<K>(pub K);
+
+impl<T, K> IntoKey<T> for ByKey<K>
+where
+    T: HasKey<Key = K>,
+{
+    fn into_key(self) -> K {
+        self.0
+    }
+}
+
+impl<T> IntoKey<T> for i64
+where
+    T: HasKey<Key = i64>,
+{
+    fn into_key(self) -> i64 {
+        self
+    }
+}
+
+impl<T> IntoKey<T> for &i64
+where
+    T: HasKey<Key = i64>,
+{
+    fn into_key(self) -> i64 {
+        *self
+    }
+}
+
+impl<T> IntoKey<T> for String
+where
+    T: HasKey<Key = String>,
+{
+    fn into_key(self) -> String {
+        self
+    }
+}
+
+impl<T> IntoKey<T> for &String
+where
+    T: HasKey<Key = String>,
+{
+    fn into_key(self) -> String {
+        self.clone()
+    }
+}
+
+impl<T> IntoKey<T> for &str
+where
+    T: HasKey<Key = String>,
+{
+    fn into_key(self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T, A, B> IntoKey<T> for (A, B)
+where
+    T: HasKey<Key = (A, B)>,
+{
+    fn into_key(self) -> (A, B) {
+        self
+    }
+}
+
+impl<T, A, B> IntoKey<T> for &(A, B)
+where
+    T: HasKey<Key = (A, B)>,
+    A: Clone,
+    B: Clone,
+{
+    fn into_key(self) -> (A, B) {
+        self.clone()
+    }
+}
+
+impl<T, A, B, C> IntoKey<T> for (A, B, C)
+where
+    T: HasKey<Key = (A, B, C)>,
+{
+    fn into_key(self) -> (A, B, C) {
+        self
+    }
+}
+
+impl<T, A, B, C> IntoKey<T> for &(A, B, C)
+where
+    T: HasKey<Key = (A, B, C)>,
+    A: Clone,
+    B: Clone,
+    C: Clone,
+{
+    fn into_key(self) -> (A, B, C) {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::KeyedManyToManyJoin;
+    use crate::KeyedTable;
+    use crate::has_key::ByKey;
+    use crate::has_key::HasKey;
+
+    struct Artist {
+        mbid: String,
+    }
+
+    impl HasKey for Artist {
+        type Key = String;
+
+        fn key(&self) -> Self::Key {
+            self.mbid.clone()
+        }
+    }
+
+    #[test]
+    fn text_key_join_test() {
+        let mut join = KeyedManyToManyJoin::<Artist, i64>::default();
+        join.add_left(Artist {
+            mbid: "a".to_string(),
+        });
+        join.add_right(1);
+        join.add_right(2);
+        join.add_relation_ids("a", 1);
+        join.add_relation_ids("a", 2);
+
+        assert_eq!(join.get_left("a").unwrap().mbid, "a");
+        assert_eq!(join.get_associated_rights_by_id("a").len(), 2);
+        assert_eq!(join.get_associated_lefts_by_id(2)[0].mbid, "a");
+    }
+
+    /// A key type that this crate knows nothing about
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct Mbid(u128);
+
+    struct Release {
+        mbid: Mbid,
+    }
+
+    impl HasKey for Release {
+        type Key = Mbid;
+
+        fn key(&self) -> Self::Key {
+            self.mbid.clone()
+        }
+    }
+
+    #[test]
+    fn foreign_key_type_test() {
+        let mut releases: KeyedTable<Release> = KeyedTable::default();
+        releases.insert(Release { mbid: Mbid(1) });
+        assert!(releases.get(ByKey(Mbid(1))).is_some());
+
+        let mut join = KeyedManyToManyJoin::<Release, i64>::default();
+        join.add_left(Release { mbid: Mbid(1) });
+        join.add_right(1);
+        join.add_relation_ids(ByKey(Mbid(1)), 1);
+        assert_eq!(join.get_associated_lefts_by_id(1).len(), 1);
+    }
+}
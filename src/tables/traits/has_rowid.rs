@@ -3,12 +3,38 @@ use chrono::DateTime;
 #[cfg(feature = "chrono")]
 use chrono::Utc;
 
+use crate::RowId;
+
+/// Derive [`HasRowID`](trait@HasRowID) on a struct, using its `id`, `rowid`, or `#[rowid]` field
+#[cfg(feature = "derive")]
+pub use sequelles_derive::HasRowID;
+/// Derive [`SetRowID`](trait@SetRowID) on a struct, using the same field as [`HasRowID`](macro@HasRowID)
+#[cfg(feature = "derive")]
+pub use sequelles_derive::SetRowID;
+
 /// Trait for all row structs that have a row ID.
 /// This is a unique incremental integer above 0.
 ///
 /// A row id of 0 is considered as a row that isn't inserted in the database yet.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't have a rowid",
+    note = "rowids are `i64`. Other row structs can be used by implementing `HasRowID` on them"
+)]
 pub trait HasRowID {
     fn rowid(&self) -> i64;
+
+    /// The rowid, typed after the row
+    fn row_id(&self) -> RowId<Self>
+    where
+        Self: Sized,
+    {
+        RowId::new(self.rowid())
+    }
+}
+
+/// Rows whose rowid can be changed, like when a new row receives the rowid given by the database
+pub trait SetRowID: HasRowID {
+    fn set_rowid(&mut self, rowid: i64);
 }
 
 impl HasRowID for i64 {
@@ -17,6 +43,12 @@ impl HasRowID for i64 {
     }
 }
 
+impl SetRowID for i64 {
+    fn set_rowid(&mut self, rowid: i64) {
+        *self = rowid;
+    }
+}
+
 impl<T> HasRowID for &T
 where
     T: HasRowID,
@@ -44,3 +76,63 @@ impl HasRowID for DateTime<Utc> {
         self.timestamp()
     }
 }
+
+#[cfg(all(test, feature = "derive"))]
+mod test {
+    use crate::JoinRelation;
+    use crate::has_rowid::HasRowID;
+    use crate::has_rowid::SetRowID;
+
+    #[derive(HasRowID, SetRowID)]
+    struct Recording {
+        id: i64,
+        #[expect(dead_code)]
+        title: String,
+    }
+
+    #[derive(HasRowID)]
+    struct Listen {
+        #[rowid]
+        listen_id: i64,
+        #[expect(dead_code)]
+        id: String,
+    }
+
+    #[derive(HasRowID)]
+    struct RecordingId(i64);
+
+    #[derive(HasRowID)]
+    struct WithRecording<T> {
+        #[rowid]
+        recording: T,
+        #[expect(dead_code)]
+        relation: JoinRelation<()>,
+    }
+
+    #[test]
+    fn derive_test() {
+        let mut recording = Recording {
+            id: 0,
+            title: String::new(),
+        };
+        recording.set_rowid(1);
+        assert_eq!(recording.rowid(), 1);
+        assert_eq!(
+            Listen {
+                listen_id: 2,
+                id: String::new()
+            }
+            .rowid(),
+            2
+        );
+        assert_eq!(RecordingId(3).rowid(), 3);
+        assert_eq!(
+            WithRecording {
+                recording,
+                relation: JoinRelation::new((), 4)
+            }
+            .rowid(),
+            1
+        );
+    }
+}
@@ -1 +1,3 @@
+pub mod has_key;
 pub mod has_rowid;
+pub mod row_id;
@@ -0,0 +1,214 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::Hash;
+use core::hash::Hasher;
+use core::marker::PhantomData;
+
+use sqlx::Decode;
+use sqlx::Encode;
+use sqlx::Type;
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+
+use crate::has_key::HasKey;
+use crate::has_key::IntoKey;
+use crate::has_rowid::HasRowID;
+
+/// The rowid of a row of type `T`.
+///
+/// This is an [`i64`] that remembers which table it belongs to, so the rowid of a recording can't be used where the rowid of an artist is expected.
+/// The rowid based APIs accept it through [`IntoKey`].
+/// It is encoded and decoded by sqlx as an [`i64`].
+///
+/// ```compile_fail
+/// # use sequelles::RowId;
+/// # use sequelles::Table;
+/// struct Artist;
+/// struct Recording;
+/// # impl sequelles::has_rowid::HasRowID for Recording {
+/// #     fn rowid(&self) -> i64 {
+/// #         0
+/// #     }
+/// # }
+///
+/// let recordings: Table<Recording> = Table::new();
+/// recordings.get(RowId::<Artist>::new(1));
+/// ```
+pub struct RowId<T>(i64, PhantomData<fn() -> T>);
+
+impl<T> RowId<T> {
+    pub const fn new(rowid: i64) -> Self {
+        Self(rowid, PhantomData)
+    }
+
+    /// Return the untyped rowid
+    pub const fn get(self) -> i64 {
+        self.0
+    }
+
+    /// Change the type of the row. This should only be used when both types represent the same table
+    pub const fn cast<U>(self) -> RowId<U> {
+        RowId::new(self.0)
+    }
+}
+
+impl<T> IntoKey<T> for RowId<T>
+where
+    T: HasKey<Key = i64>,
+{
+    fn into_key(self) -> i64 {
+        self.0
+    }
+}
+
+impl<T> IntoKey<T> for &RowId<T>
+where
+    T: HasKey<Key = i64>,
+{
+    fn into_key(self) -> i64 {
+        self.0
+    }
+}
+
+impl<T> HasRowID for RowId<T> {
+    fn rowid(&self) -> i64 {
+        self.0
+    }
+}
+
+impl<T> From<RowId<T>> for i64 {
+    fn from(value: RowId<T>) -> Self {
+        value.0
+    }
+}
+
+// Manual impls, as deriving would require the bounds on `T`
+impl<T> Clone for RowId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RowId<T> {}
+
+impl<T> PartialEq for RowId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for RowId<T> {}
+
+impl<T> PartialOrd for RowId<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for RowId<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<T> Hash for RowId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for RowId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RowId").field(&self.0).finish()
+    }
+}
+
+impl<T> fmt::Display for RowId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T, DB> Type<DB> for RowId<T>
+where
+    DB: sqlx::Database,
+    i64: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <i64 as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <i64 as Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'q, T, DB> Encode<'q, DB> for RowId<T>
+where
+    DB: sqlx::Database,
+    i64: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as sqlx::Database>::ArgumentBuffer<'q>,
+    ) -> Result<IsNull, BoxDynError> {
+        self.0.encode_by_ref(buf)
+    }
+
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+}
+
+impl<'r, T, DB> Decode<'r, DB> for RowId<T>
+where
+    DB: sqlx::Database,
+    i64: Decode<'r, DB>,
+{
+    fn decode(value: <DB as sqlx::Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        i64::decode(value).map(Self::new)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for RowId<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for RowId<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        i64::deserialize(deserializer).map(Self::new)
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+    use sqlx::Connection as _;
+    use sqlx::SqliteConnection;
+
+    use crate::RowId;
+
+    struct Artist;
+
+    #[tokio::test]
+    async fn row_id_sqlx_test() {
+        let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+
+        let id: RowId<Artist> = sqlx::query_scalar("SELECT ? + 1")
+            .bind(RowId::<Artist>::new(41))
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(id.get(), 42);
+    }
+}